@@ -21,6 +21,8 @@ fn main() {
         .probe("libzfs")
         .unwrap();
     println!("cargo:rustc-link-lib=zpool");
+    // For lzc_snapshot, which reports an error per snapshot
+    println!("cargo:rustc-link-lib=zfs_core");
 
     // Skip building if bindings already exist.
     // If you want to rebuild, delete the bindings file.
@@ -33,11 +35,16 @@ fn main() {
         .constified_enum_module("boolean")
         .whitelist_var("vdev_stat_t")
         .whitelist_type("vdev_stat_t")
+        .whitelist_type("pool_scan_stat_t")
+        .whitelist_type("dsl_scan_state_t")
         .whitelist_var("ZPOOL_MAXPROPLEN")
         .whitelist_var("ZPOOL_CONFIG_POOL_NAME")
+        .whitelist_var("ZPOOL_CONFIG_POOL_GUID")
         .whitelist_var("ZPOOL_CONFIG_TYPE")
         .whitelist_var("ZPOOL_CONFIG_VDEV_TREE")
         .whitelist_var("ZPOOL_CONFIG_CHILDREN")
+        .whitelist_var("ZPOOL_CONFIG_NPARITY")
+        .whitelist_var("ZPOOL_CONFIG_ASHIFT")
         .whitelist_var("ZPOOL_CONFIG_SPARES")
         .whitelist_var("ZPOOL_CONFIG_L2CACHE")
         .whitelist_var("ZPOOL_CONFIG_PATH")
@@ -45,11 +52,19 @@ fn main() {
         .whitelist_var("ZPOOL_CONFIG_DEVID")
         .whitelist_var("ZPOOL_CONFIG_WHOLE_DISK")
         .whitelist_var("ZPOOL_CONFIG_IS_LOG")
+        .whitelist_var("ZPOOL_CONFIG_IS_SPARE")
         .whitelist_var("ZPOOL_CONFIG_HOSTID")
         .whitelist_var("ZPOOL_CONFIG_HOSTNAME")
         .whitelist_var("ZPOOL_CONFIG_GUID")
         .whitelist_var("ZPOOL_CONFIG_AUX_STATE")
+        .whitelist_var("ZPOOL_CONFIG_POOL_STATE")
+        .whitelist_var("ZPOOL_CONFIG_POOL_TXG")
+        .whitelist_var("ZPOOL_CONFIG_LOAD_INFO")
+        .whitelist_var("ZPOOL_CONFIG_MMP_STATE")
+        .whitelist_var("ZPOOL_CONFIG_MMP_HOSTNAME")
+        .whitelist_var("ZPOOL_CONFIG_MMP_HOSTID")
         .whitelist_var("ZPOOL_CONFIG_VDEV_STATS")
+        .whitelist_var("ZPOOL_CONFIG_SCAN_STATS")
         .whitelist_var("VDEV_TYPE_ROOT")
         .whitelist_var("VDEV_TYPE_MIRROR")
         .whitelist_var("VDEV_TYPE_REPLACING")
@@ -64,10 +79,21 @@ fn main() {
         .whitelist_var("ZPROP_VALUE")
         .whitelist_var("ZFS_MAXPROPLEN")
         .whitelist_var("ZFS_MAX_DATASET_NAME_LEN")
+        .whitelist_var("ZFS_IMPORT_NORMAL")
+        .whitelist_var("ZFS_IMPORT_VERBATIM")
+        .whitelist_var("ZFS_IMPORT_ANY_HOST")
+        .whitelist_var("ZFS_IMPORT_MISSING_LOG")
+        .whitelist_var("ZFS_IMPORT_ONLY")
+        .whitelist_var("ZFS_ONLINE_CHECKREMOVE")
+        .whitelist_var("ZFS_ONLINE_UNSPARE")
+        .whitelist_var("ZFS_ONLINE_FORCEFAULT")
+        .whitelist_var("ZFS_ONLINE_EXPAND")
         .whitelist_type("zpool_prop_t")
         .constified_enum_module("zpool_prop_t")
         .whitelist_type("pool_state_t")
         .constified_enum_module("pool_state")
+        .whitelist_type("zfs_error_t")
+        .whitelist_type("mmp_state_t")
         .bitfield_enum("zfs_type_t")
         .opaque_type("libzfs_handle_t")
         .blacklist_type("nvlist_t")
@@ -76,31 +102,60 @@ fn main() {
         .whitelist_function("libzfs_fini")
         .whitelist_function("thread_init")
         .whitelist_function("thread_fini")
+        .whitelist_function("zpool_create")
+        .whitelist_function("zpool_destroy")
         .whitelist_function("zpool_import")
+        .whitelist_function("zpool_import_props")
+        .whitelist_function("zpool_import_status")
         .whitelist_function("zpool_export")
+        .whitelist_function("zpool_export_force")
         .whitelist_function("zpool_search_import")
         .whitelist_function("zpool_iter")
         .whitelist_function("zpool_open_canfail")
         .whitelist_function("zpool_close")
         .whitelist_function("zpool_get_name")
         .whitelist_function("zpool_get_state")
+        .whitelist_function("zpool_scan")
         .whitelist_function("zpool_pool_state_to_name")
         .whitelist_function("zpool_get_prop_int")
         .whitelist_function("zpool_get_prop")
         .whitelist_function("zpool_get_config")
         .whitelist_function("zpool_get_handle")
+        .whitelist_function("zpool_refresh_stats")
+        .whitelist_function("zpool_find_vdev")
+        .whitelist_function("zpool_vdev_online")
+        .whitelist_function("zpool_vdev_offline")
+        .whitelist_function("zpool_vdev_attach")
+        .whitelist_function("zpool_vdev_detach")
+        .whitelist_function("zpool_add")
+        .whitelist_function("zpool_vdev_remove")
+        .whitelist_function("zpool_clear")
         .whitelist_function("zpool_state_to_name")
+        .whitelist_function("get_system_hostid")
         .whitelist_function("zfs_open")
         .whitelist_function("zfs_close")
         .whitelist_function("zfs_iter_filesystems")
+        .whitelist_function("zfs_iter_snapshots")
+        .whitelist_function("zfs_iter_snapshots_sorted")
+        .whitelist_function("zfs_iter_bookmarks")
+        .whitelist_function("zfs_iter_dependents")
         .whitelist_function("zfs_get_name")
         .whitelist_function("zfs_get_user_props")
         .whitelist_function("zfs_get_type")
         .whitelist_function("zfs_type_to_name")
         .whitelist_function("zfs_path_to_zhandle")
+        .whitelist_function("zfs_get_handle")
+        .whitelist_function("zfs_snapshot")
+        .whitelist_function("zfs_snapshot_nvl")
+        .whitelist_function("lzc_snapshot")
+        .whitelist_function("zfs_rollback")
+        .whitelist_function("zpool_enable_datasets")
         .whitelist_function("zpool_disable_datasets")
+        .whitelist_function("libzfs_errno")
+        .whitelist_function("libzfs_error_action")
         .whitelist_function("libzfs_error_description")
         .whitelist_function("zfs_prop_get")
+        .whitelist_function("zfs_prop_get_int")
         .whitelist_function("zfs_expand_proplist")
         .whitelist_function("zfs_prop_to_name")
         .whitelist_function("zfs_validate_name")
@@ -84,21 +84,32 @@ where
 pub const ZFS_MAX_DATASET_NAME_LEN: u32 = 256;
 pub const ZPROP_VALUE: &'static [u8; 6usize] = b"value\0";
 pub const ZPOOL_CONFIG_POOL_NAME: &'static [u8; 5usize] = b"name\0";
+pub const ZPOOL_CONFIG_POOL_GUID: &'static [u8; 10usize] = b"pool_guid\0";
 pub const ZPOOL_CONFIG_VDEV_TREE: &'static [u8; 10usize] = b"vdev_tree\0";
 pub const ZPOOL_CONFIG_TYPE: &'static [u8; 5usize] = b"type\0";
 pub const ZPOOL_CONFIG_CHILDREN: &'static [u8; 9usize] = b"children\0";
+pub const ZPOOL_CONFIG_NPARITY: &'static [u8; 8usize] = b"nparity\0";
+pub const ZPOOL_CONFIG_ASHIFT: &'static [u8; 7usize] = b"ashift\0";
 pub const ZPOOL_CONFIG_GUID: &'static [u8; 5usize] = b"guid\0";
 pub const ZPOOL_CONFIG_PATH: &'static [u8; 5usize] = b"path\0";
 pub const ZPOOL_CONFIG_DEVID: &'static [u8; 6usize] = b"devid\0";
 pub const ZPOOL_CONFIG_VDEV_STATS: &'static [u8; 11usize] = b"vdev_stats\0";
+pub const ZPOOL_CONFIG_SCAN_STATS: &'static [u8; 11usize] = b"scan_stats\0";
 pub const ZPOOL_CONFIG_WHOLE_DISK: &'static [u8; 11usize] = b"whole_disk\0";
 pub const ZPOOL_CONFIG_SPARES: &'static [u8; 7usize] = b"spares\0";
 pub const ZPOOL_CONFIG_HOSTID: &'static [u8; 7usize] = b"hostid\0";
 pub const ZPOOL_CONFIG_HOSTNAME: &'static [u8; 9usize] = b"hostname\0";
 pub const ZPOOL_CONFIG_PHYS_PATH: &'static [u8; 10usize] = b"phys_path\0";
 pub const ZPOOL_CONFIG_IS_LOG: &'static [u8; 7usize] = b"is_log\0";
+pub const ZPOOL_CONFIG_IS_SPARE: &'static [u8; 9usize] = b"is_spare\0";
 pub const ZPOOL_CONFIG_L2CACHE: &'static [u8; 8usize] = b"l2cache\0";
 pub const ZPOOL_CONFIG_AUX_STATE: &'static [u8; 10usize] = b"aux_state\0";
+pub const ZPOOL_CONFIG_POOL_STATE: &'static [u8; 6usize] = b"state\0";
+pub const ZPOOL_CONFIG_POOL_TXG: &'static [u8; 4usize] = b"txg\0";
+pub const ZPOOL_CONFIG_LOAD_INFO: &'static [u8; 10usize] = b"load_info\0";
+pub const ZPOOL_CONFIG_MMP_STATE: &'static [u8; 10usize] = b"mmp_state\0";
+pub const ZPOOL_CONFIG_MMP_HOSTNAME: &'static [u8; 13usize] = b"mmp_hostname\0";
+pub const ZPOOL_CONFIG_MMP_HOSTID: &'static [u8; 11usize] = b"mmp_hostid\0";
 pub const VDEV_TYPE_ROOT: &'static [u8; 5usize] = b"root\0";
 pub const VDEV_TYPE_MIRROR: &'static [u8; 7usize] = b"mirror\0";
 pub const VDEV_TYPE_REPLACING: &'static [u8; 10usize] = b"replacing\0";
@@ -111,6 +122,15 @@ pub const VDEV_TYPE_SPARE: &'static [u8; 6usize] = b"spare\0";
 pub const VDEV_TYPE_LOG: &'static [u8; 4usize] = b"log\0";
 pub const VDEV_TYPE_L2CACHE: &'static [u8; 8usize] = b"l2cache\0";
 pub const ZFS_MAXPROPLEN: u32 = 4096;
+pub const ZFS_IMPORT_NORMAL: u32 = 0;
+pub const ZFS_IMPORT_VERBATIM: u32 = 1;
+pub const ZFS_IMPORT_ANY_HOST: u32 = 2;
+pub const ZFS_IMPORT_MISSING_LOG: u32 = 4;
+pub const ZFS_IMPORT_ONLY: u32 = 8;
+pub const ZFS_ONLINE_CHECKREMOVE: u32 = 1;
+pub const ZFS_ONLINE_UNSPARE: u32 = 2;
+pub const ZFS_ONLINE_FORCEFAULT: u32 = 4;
+pub const ZFS_ONLINE_EXPAND: u32 = 8;
 pub const ZPOOL_MAXPROPLEN: u32 = 4096;
 pub mod boolean {
     pub type Type = u32;
@@ -550,6 +570,190 @@ fn bindgen_test_layout_vdev_stat() {
     );
 }
 pub type vdev_stat_t = vdev_stat;
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct pool_scan_stat {
+    pub pss_func: u64,
+    pub pss_state: u64,
+    pub pss_start_time: u64,
+    pub pss_end_time: u64,
+    pub pss_to_examine: u64,
+    pub pss_examined: u64,
+    pub pss_to_process: u64,
+    pub pss_processed: u64,
+    pub pss_errors: u64,
+    pub pss_pass_exam: u64,
+    pub pss_pass_start: u64,
+    pub pss_pass_scrub_pause: u64,
+    pub pss_pass_scrub_spent_paused: u64,
+}
+#[test]
+fn bindgen_test_layout_pool_scan_stat() {
+    assert_eq!(
+        ::std::mem::size_of::<pool_scan_stat>(),
+        104usize,
+        concat!("Size of: ", stringify!(pool_scan_stat))
+    );
+    assert_eq!(
+        ::std::mem::align_of::<pool_scan_stat>(),
+        8usize,
+        concat!("Alignment of ", stringify!(pool_scan_stat))
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_func as *const _ as usize },
+        0usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_func)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_state as *const _ as usize },
+        8usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_state)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_start_time as *const _ as usize },
+        16usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_start_time)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_end_time as *const _ as usize },
+        24usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_end_time)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_to_examine as *const _ as usize },
+        32usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_to_examine)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_examined as *const _ as usize },
+        40usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_examined)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_to_process as *const _ as usize },
+        48usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_to_process)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_processed as *const _ as usize },
+        56usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_processed)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_errors as *const _ as usize },
+        64usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_errors)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_pass_exam as *const _ as usize },
+        72usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_pass_exam)
+        )
+    );
+    assert_eq!(
+        unsafe { &(*(::std::ptr::null::<pool_scan_stat>())).pss_pass_start as *const _ as usize },
+        80usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_pass_start)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<pool_scan_stat>())).pss_pass_scrub_pause as *const _ as usize
+        },
+        88usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_pass_scrub_pause)
+        )
+    );
+    assert_eq!(
+        unsafe {
+            &(*(::std::ptr::null::<pool_scan_stat>())).pss_pass_scrub_spent_paused as *const _
+                as usize
+        },
+        96usize,
+        concat!(
+            "Offset of field: ",
+            stringify!(pool_scan_stat),
+            "::",
+            stringify!(pss_pass_scrub_spent_paused)
+        )
+    );
+}
+pub type pool_scan_stat_t = pool_scan_stat;
+pub const pool_scan_func_POOL_SCAN_NONE: pool_scan_func = 0;
+pub const pool_scan_func_POOL_SCAN_SCRUB: pool_scan_func = 1;
+pub const pool_scan_func_POOL_SCAN_RESILVER: pool_scan_func = 2;
+pub const pool_scan_func_POOL_SCAN_FUNCS: pool_scan_func = 3;
+pub type pool_scan_func = u32;
+pub use self::pool_scan_func as pool_scan_func_t;
+pub const pool_scrub_cmd_POOL_SCRUB_NORMAL: pool_scrub_cmd = 0;
+pub const pool_scrub_cmd_POOL_SCRUB_PAUSE: pool_scrub_cmd = 1;
+pub const pool_scrub_cmd_POOL_SCRUB_FLAGS_END: pool_scrub_cmd = 2;
+pub type pool_scrub_cmd = u32;
+pub use self::pool_scrub_cmd as pool_scrub_cmd_t;
+pub const dsl_scan_state_DSS_NONE: dsl_scan_state = 0;
+pub const dsl_scan_state_DSS_SCANNING: dsl_scan_state = 1;
+pub const dsl_scan_state_DSS_FINISHED: dsl_scan_state = 2;
+pub const dsl_scan_state_DSS_CANCELED: dsl_scan_state = 3;
+pub const dsl_scan_state_DSS_NUM_STATES: dsl_scan_state = 4;
+pub type dsl_scan_state = u32;
+pub use self::dsl_scan_state as dsl_scan_state_t;
 extern "C" {
     pub fn thread_init();
 }
@@ -657,6 +861,121 @@ fn bindgen_test_layout_dmu_objset_stats() {
     );
 }
 pub type dmu_objset_stats_t = dmu_objset_stats;
+pub const zfs_error_EZFS_SUCCESS: zfs_error = 0;
+pub const zfs_error_EZFS_NOMEM: zfs_error = 2000;
+pub const zfs_error_EZFS_BADPROP: zfs_error = 2001;
+pub const zfs_error_EZFS_PROPREADONLY: zfs_error = 2002;
+pub const zfs_error_EZFS_PROPTYPE: zfs_error = 2003;
+pub const zfs_error_EZFS_PROPNONINHERIT: zfs_error = 2004;
+pub const zfs_error_EZFS_PROPSPACE: zfs_error = 2005;
+pub const zfs_error_EZFS_BADTYPE: zfs_error = 2006;
+pub const zfs_error_EZFS_BUSY: zfs_error = 2007;
+pub const zfs_error_EZFS_EXISTS: zfs_error = 2008;
+pub const zfs_error_EZFS_NOENT: zfs_error = 2009;
+pub const zfs_error_EZFS_BADSTREAM: zfs_error = 2010;
+pub const zfs_error_EZFS_DSREADONLY: zfs_error = 2011;
+pub const zfs_error_EZFS_VOLTOOBIG: zfs_error = 2012;
+pub const zfs_error_EZFS_INVALIDNAME: zfs_error = 2013;
+pub const zfs_error_EZFS_BADRESTORE: zfs_error = 2014;
+pub const zfs_error_EZFS_BADBACKUP: zfs_error = 2015;
+pub const zfs_error_EZFS_BADTARGET: zfs_error = 2016;
+pub const zfs_error_EZFS_NODEVICE: zfs_error = 2017;
+pub const zfs_error_EZFS_BADDEV: zfs_error = 2018;
+pub const zfs_error_EZFS_NOREPLICAS: zfs_error = 2019;
+pub const zfs_error_EZFS_RESILVERING: zfs_error = 2020;
+pub const zfs_error_EZFS_BADVERSION: zfs_error = 2021;
+pub const zfs_error_EZFS_POOLUNAVAIL: zfs_error = 2022;
+pub const zfs_error_EZFS_DEVOVERFLOW: zfs_error = 2023;
+pub const zfs_error_EZFS_BADPATH: zfs_error = 2024;
+pub const zfs_error_EZFS_CROSSTARGET: zfs_error = 2025;
+pub const zfs_error_EZFS_ZONED: zfs_error = 2026;
+pub const zfs_error_EZFS_MOUNTFAILED: zfs_error = 2027;
+pub const zfs_error_EZFS_UMOUNTFAILED: zfs_error = 2028;
+pub const zfs_error_EZFS_UNSHARENFSFAILED: zfs_error = 2029;
+pub const zfs_error_EZFS_SHARENFSFAILED: zfs_error = 2030;
+pub const zfs_error_EZFS_PERM: zfs_error = 2031;
+pub const zfs_error_EZFS_NOSPC: zfs_error = 2032;
+pub const zfs_error_EZFS_FAULT: zfs_error = 2033;
+pub const zfs_error_EZFS_IO: zfs_error = 2034;
+pub const zfs_error_EZFS_INTR: zfs_error = 2035;
+pub const zfs_error_EZFS_ISSPARE: zfs_error = 2036;
+pub const zfs_error_EZFS_INVALCONFIG: zfs_error = 2037;
+pub const zfs_error_EZFS_RECURSIVE: zfs_error = 2038;
+pub const zfs_error_EZFS_NOHISTORY: zfs_error = 2039;
+pub const zfs_error_EZFS_POOLPROPS: zfs_error = 2040;
+pub const zfs_error_EZFS_POOL_NOTSUP: zfs_error = 2041;
+pub const zfs_error_EZFS_POOL_INVALARG: zfs_error = 2042;
+pub const zfs_error_EZFS_NAMETOOLONG: zfs_error = 2043;
+pub const zfs_error_EZFS_OPENFAILED: zfs_error = 2044;
+pub const zfs_error_EZFS_NOCAP: zfs_error = 2045;
+pub const zfs_error_EZFS_LABELFAILED: zfs_error = 2046;
+pub const zfs_error_EZFS_BADWHO: zfs_error = 2047;
+pub const zfs_error_EZFS_BADPERM: zfs_error = 2048;
+pub const zfs_error_EZFS_BADPERMSET: zfs_error = 2049;
+pub const zfs_error_EZFS_NODELEGATION: zfs_error = 2050;
+pub const zfs_error_EZFS_UNSHARESMBFAILED: zfs_error = 2051;
+pub const zfs_error_EZFS_SHARESMBFAILED: zfs_error = 2052;
+pub const zfs_error_EZFS_BADCACHE: zfs_error = 2053;
+pub const zfs_error_EZFS_ISL2CACHE: zfs_error = 2054;
+pub const zfs_error_EZFS_VDEVNOTSUP: zfs_error = 2055;
+pub const zfs_error_EZFS_NOTSUP: zfs_error = 2056;
+pub const zfs_error_EZFS_ACTIVE_SPARE: zfs_error = 2057;
+pub const zfs_error_EZFS_UNPLAYED_LOGS: zfs_error = 2058;
+pub const zfs_error_EZFS_REFTAG_RELE: zfs_error = 2059;
+pub const zfs_error_EZFS_REFTAG_HOLD: zfs_error = 2060;
+pub const zfs_error_EZFS_TAGTOOLONG: zfs_error = 2061;
+pub const zfs_error_EZFS_PIPEFAILED: zfs_error = 2062;
+pub const zfs_error_EZFS_THREADCREATEFAILED: zfs_error = 2063;
+pub const zfs_error_EZFS_POSTSPLIT_ONLINE: zfs_error = 2064;
+pub const zfs_error_EZFS_SCRUBBING: zfs_error = 2065;
+pub const zfs_error_EZFS_NO_SCRUB: zfs_error = 2066;
+pub const zfs_error_EZFS_DIFF: zfs_error = 2067;
+pub const zfs_error_EZFS_DIFFDATA: zfs_error = 2068;
+pub const zfs_error_EZFS_POOLREADONLY: zfs_error = 2069;
+pub const zfs_error_EZFS_SCRUB_PAUSED: zfs_error = 2070;
+pub const zfs_error_EZFS_ACTIVE_POOL: zfs_error = 2071;
+pub const zfs_error_EZFS_UNKNOWN: zfs_error = 2072;
+pub type zfs_error = u32;
+pub use self::zfs_error as zfs_error_t;
+pub const mmp_state_MMP_STATE_ACTIVE: mmp_state = 0;
+pub const mmp_state_MMP_STATE_INACTIVE: mmp_state = 1;
+pub const mmp_state_MMP_STATE_NO_HOSTID: mmp_state = 2;
+pub type mmp_state = u32;
+pub use self::mmp_state as mmp_state_t;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_CACHE: zpool_status_t = 0;
+pub const zpool_status_t_ZPOOL_STATUS_MISSING_DEV_R: zpool_status_t = 1;
+pub const zpool_status_t_ZPOOL_STATUS_MISSING_DEV_NR: zpool_status_t = 2;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_R: zpool_status_t = 3;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_NR: zpool_status_t = 4;
+pub const zpool_status_t_ZPOOL_STATUS_BAD_GUID_SUM: zpool_status_t = 5;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_POOL: zpool_status_t = 6;
+pub const zpool_status_t_ZPOOL_STATUS_CORRUPT_DATA: zpool_status_t = 7;
+pub const zpool_status_t_ZPOOL_STATUS_FAILING_DEV: zpool_status_t = 8;
+pub const zpool_status_t_ZPOOL_STATUS_VERSION_NEWER: zpool_status_t = 9;
+pub const zpool_status_t_ZPOOL_STATUS_HOSTID_MISMATCH: zpool_status_t = 10;
+pub const zpool_status_t_ZPOOL_STATUS_HOSTID_ACTIVE: zpool_status_t = 11;
+pub const zpool_status_t_ZPOOL_STATUS_HOSTID_REQUIRED: zpool_status_t = 12;
+pub const zpool_status_t_ZPOOL_STATUS_IO_FAILURE_WAIT: zpool_status_t = 13;
+pub const zpool_status_t_ZPOOL_STATUS_IO_FAILURE_CONTINUE: zpool_status_t = 14;
+pub const zpool_status_t_ZPOOL_STATUS_IO_FAILURE_MMP: zpool_status_t = 15;
+pub const zpool_status_t_ZPOOL_STATUS_BAD_LOG: zpool_status_t = 16;
+pub const zpool_status_t_ZPOOL_STATUS_ERRATA: zpool_status_t = 17;
+pub const zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_READ: zpool_status_t = 18;
+pub const zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_WRITE: zpool_status_t = 19;
+pub const zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_R: zpool_status_t = 20;
+pub const zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_NR: zpool_status_t = 21;
+pub const zpool_status_t_ZPOOL_STATUS_VERSION_OLDER: zpool_status_t = 22;
+pub const zpool_status_t_ZPOOL_STATUS_FEAT_DISABLED: zpool_status_t = 23;
+pub const zpool_status_t_ZPOOL_STATUS_RESILVERING: zpool_status_t = 24;
+pub const zpool_status_t_ZPOOL_STATUS_OFFLINE_DEV: zpool_status_t = 25;
+pub const zpool_status_t_ZPOOL_STATUS_REMOVED_DEV: zpool_status_t = 26;
+pub const zpool_status_t_ZPOOL_STATUS_OK: zpool_status_t = 27;
+pub type zpool_status_t = u32;
+pub const zpool_errata_ZPOOL_ERRATA_NONE: zpool_errata = 0;
+pub const zpool_errata_ZPOOL_ERRATA_ZOL_2094_SCRUB: zpool_errata = 1;
+pub const zpool_errata_ZPOOL_ERRATA_ZOL_2094_ASYNC_DESTROY: zpool_errata = 2;
+pub type zpool_errata = u32;
+pub use self::zpool_errata as zpool_errata_t;
 pub type zfs_handle_t = zfs_handle;
 pub type zpool_handle_t = zpool_handle;
 pub type libzfs_handle_t = [u64; 305usize];
@@ -669,6 +988,73 @@ extern "C" {
 extern "C" {
     pub fn zpool_get_handle(arg1: *mut zpool_handle_t) -> *mut libzfs_handle_t;
 }
+extern "C" {
+    pub fn zpool_refresh_stats(
+        arg1: *mut zpool_handle_t,
+        arg2: *mut boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_find_vdev(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut boolean_t,
+        arg4: *mut boolean_t,
+        arg5: *mut boolean_t,
+    ) -> *mut nvlist_t;
+}
+extern "C" {
+    pub fn zpool_vdev_online(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: ::std::os::raw::c_int,
+        arg4: *mut vdev_state_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_offline(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_attach(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *const ::std::os::raw::c_char,
+        arg4: *mut nvlist_t,
+        arg5: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_detach(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_add(arg1: *mut zpool_handle_t, arg2: *mut nvlist_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_vdev_remove(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_clear(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libzfs_errno(arg1: *mut libzfs_handle_t) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn libzfs_error_action(arg1: *mut libzfs_handle_t) -> *const ::std::os::raw::c_char;
+}
 extern "C" {
     pub fn libzfs_error_description(arg1: *mut libzfs_handle_t) -> *const ::std::os::raw::c_char;
 }
@@ -684,6 +1070,13 @@ extern "C" {
 extern "C" {
     pub fn zpool_get_name(arg1: *mut zpool_handle_t) -> *const ::std::os::raw::c_char;
 }
+extern "C" {
+    pub fn zpool_scan(
+        arg1: *mut zpool_handle_t,
+        arg2: pool_scan_func_t,
+        arg3: pool_scrub_cmd_t,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn zpool_get_state(arg1: *mut zpool_handle_t) -> ::std::os::raw::c_int;
 }
@@ -691,6 +1084,9 @@ extern "C" {
     pub fn zpool_state_to_name(arg1: vdev_state_t, arg2: vdev_aux_t)
         -> *mut ::std::os::raw::c_char;
 }
+extern "C" {
+    pub fn get_system_hostid() -> ::std::os::raw::c_ulong;
+}
 extern "C" {
     pub fn zpool_pool_state_to_name(arg1: pool_state_t) -> *const ::std::os::raw::c_char;
 }
@@ -707,6 +1103,21 @@ extern "C" {
         arg3: *mut ::std::os::raw::c_void,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn zpool_create(
+        arg1: *mut libzfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: *mut nvlist_t,
+        arg4: *mut nvlist_t,
+        arg5: *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_destroy(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn zpool_get_prop(
         arg1: *mut zpool_handle_t,
@@ -727,6 +1138,13 @@ extern "C" {
 extern "C" {
     pub fn zpool_get_config(arg1: *mut zpool_handle_t, arg2: *mut *mut nvlist_t) -> *mut nvlist_t;
 }
+extern "C" {
+    pub fn zpool_import_status(
+        arg1: *mut nvlist_t,
+        arg2: *mut *mut ::std::os::raw::c_char,
+        arg3: *mut zpool_errata_t,
+    ) -> zpool_status_t;
+}
 extern "C" {
     pub fn zpool_export(
         arg1: *mut zpool_handle_t,
@@ -734,6 +1152,12 @@ extern "C" {
         arg3: *const ::std::os::raw::c_char,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn zpool_export_force(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn zpool_import(
         arg1: *mut libzfs_handle_t,
@@ -742,6 +1166,15 @@ extern "C" {
         altroot: *mut ::std::os::raw::c_char,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn zpool_import_props(
+        arg1: *mut libzfs_handle_t,
+        arg2: *mut nvlist_t,
+        arg3: *const ::std::os::raw::c_char,
+        arg4: *mut nvlist_t,
+        arg5: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct importargs {
@@ -924,6 +1357,9 @@ extern "C" {
         arg8: boolean_t,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn zfs_prop_get_int(arg1: *mut zfs_handle_t, arg2: zfs_prop_t) -> u64;
+}
 extern "C" {
     pub fn zfs_get_user_props(arg1: *mut zfs_handle_t) -> *mut nvlist_t;
 }
@@ -1046,6 +1482,36 @@ extern "C" {
         arg3: *mut ::std::os::raw::c_void,
     ) -> ::std::os::raw::c_int;
 }
+extern "C" {
+    pub fn zfs_iter_snapshots(
+        arg1: *mut zfs_handle_t,
+        arg2: boolean_t,
+        arg3: zfs_iter_f,
+        arg4: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_iter_snapshots_sorted(
+        arg1: *mut zfs_handle_t,
+        arg2: zfs_iter_f,
+        arg3: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_iter_bookmarks(
+        arg1: *mut zfs_handle_t,
+        arg2: zfs_iter_f,
+        arg3: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_iter_dependents(
+        arg1: *mut zfs_handle_t,
+        arg2: boolean_t,
+        arg3: zfs_iter_f,
+        arg4: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn zfs_type_to_name(arg1: zfs_type_t) -> *const ::std::os::raw::c_char;
 }
@@ -1056,6 +1522,45 @@ extern "C" {
         arg3: zfs_type_t,
     ) -> *mut zfs_handle_t;
 }
+extern "C" {
+    pub fn zfs_get_handle(arg1: *mut zfs_handle_t) -> *mut libzfs_handle_t;
+}
+extern "C" {
+    pub fn zfs_snapshot(
+        arg1: *mut libzfs_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: boolean_t,
+        arg4: *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_snapshot_nvl(
+        arg1: *mut libzfs_handle_t,
+        arg2: *mut nvlist_t,
+        arg3: *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn lzc_snapshot(
+        arg1: *mut nvlist_t,
+        arg2: *mut nvlist_t,
+        arg3: *mut *mut nvlist_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zfs_rollback(
+        arg1: *mut zfs_handle_t,
+        arg2: *mut zfs_handle_t,
+        arg3: boolean_t,
+    ) -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn zpool_enable_datasets(
+        arg1: *mut zpool_handle_t,
+        arg2: *const ::std::os::raw::c_char,
+        arg3: ::std::os::raw::c_int,
+    ) -> ::std::os::raw::c_int;
+}
 extern "C" {
     pub fn zpool_disable_datasets(
         arg1: *mut zpool_handle_t,
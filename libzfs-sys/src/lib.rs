@@ -29,10 +29,49 @@ use nvpair_sys::*;
 
 include!("bindings.rs");
 
+// Config names and vdev types added after ZFS 0.7.
+// They are not in the headers the bindings were generated from,
+// but can show up in the configs of pools created by later versions.
+
+pub const ZPOOL_CONFIG_ALLOCATION_BIAS: &'static [u8; 11usize] = b"alloc_bias\0";
+pub const ZPOOL_CONFIG_DRAID_NDATA: &'static [u8; 12usize] = b"draid_ndata\0";
+pub const ZPOOL_CONFIG_DRAID_NSPARES: &'static [u8; 14usize] = b"draid_nspares\0";
+pub const ZPOOL_CONFIG_DRAID_NGROUPS: &'static [u8; 14usize] = b"draid_ngroups\0";
+pub const VDEV_ALLOC_BIAS_LOG: &'static [u8; 4usize] = b"log\0";
+pub const VDEV_ALLOC_BIAS_SPECIAL: &'static [u8; 8usize] = b"special\0";
+pub const VDEV_ALLOC_BIAS_DEDUP: &'static [u8; 6usize] = b"dedup\0";
+pub const VDEV_TYPE_INDIRECT: &'static [u8; 9usize] = b"indirect\0";
+pub const VDEV_TYPE_DRAID: &'static [u8; 6usize] = b"draid\0";
+pub const VDEV_TYPE_DRAID_SPARE: &'static [u8; 7usize] = b"dspare\0";
+pub const ZPOOL_CONFIG_REMOVING_STATS: &'static [u8; 15usize] = b"removing_stats\0";
+
+/// The progress of a top-level vdev removal,
+/// packed into `ZPOOL_CONFIG_REMOVING_STATS`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct pool_removal_stat {
+    pub prs_state: u64,
+    pub prs_removing_vdev: u64,
+    pub prs_start_time: u64,
+    pub prs_end_time: u64,
+    pub prs_to_copy: u64,
+    pub prs_copied: u64,
+    pub prs_mapping_memory: u64,
+}
+pub type pool_removal_stat_t = pool_removal_stat;
+
 fn utf8_to_string(bytes: &[u8]) -> String {
     String::from_utf8(bytes.to_vec()).unwrap()
 }
 
+pub fn zpool_config_pool_name() -> String {
+    utf8_to_string(ZPOOL_CONFIG_POOL_NAME)
+}
+
+pub fn zpool_config_pool_guid() -> String {
+    utf8_to_string(ZPOOL_CONFIG_POOL_GUID)
+}
+
 pub fn zpool_config_vdev_tree() -> String {
     utf8_to_string(ZPOOL_CONFIG_VDEV_TREE)
 }
@@ -45,6 +84,30 @@ pub fn zpool_config_children() -> String {
     utf8_to_string(ZPOOL_CONFIG_CHILDREN)
 }
 
+pub fn zpool_config_nparity() -> String {
+    utf8_to_string(ZPOOL_CONFIG_NPARITY)
+}
+
+pub fn zpool_config_ashift() -> String {
+    utf8_to_string(ZPOOL_CONFIG_ASHIFT)
+}
+
+pub fn zpool_config_allocation_bias() -> String {
+    utf8_to_string(ZPOOL_CONFIG_ALLOCATION_BIAS)
+}
+
+pub fn zpool_config_draid_ndata() -> String {
+    utf8_to_string(ZPOOL_CONFIG_DRAID_NDATA)
+}
+
+pub fn zpool_config_draid_nspares() -> String {
+    utf8_to_string(ZPOOL_CONFIG_DRAID_NSPARES)
+}
+
+pub fn zpool_config_draid_ngroups() -> String {
+    utf8_to_string(ZPOOL_CONFIG_DRAID_NGROUPS)
+}
+
 pub fn zpool_config_spares() -> String {
     utf8_to_string(ZPOOL_CONFIG_SPARES)
 }
@@ -69,6 +132,10 @@ pub fn zpool_config_is_log() -> String {
     utf8_to_string(ZPOOL_CONFIG_IS_LOG)
 }
 
+pub fn zpool_config_is_spare() -> String {
+    utf8_to_string(ZPOOL_CONFIG_IS_SPARE)
+}
+
 pub fn zpool_config_whole_disk() -> String {
     utf8_to_string(ZPOOL_CONFIG_WHOLE_DISK)
 }
@@ -85,6 +152,30 @@ pub fn zpool_config_guid() -> String {
     utf8_to_string(ZPOOL_CONFIG_GUID)
 }
 
+pub fn zpool_config_pool_state() -> String {
+    utf8_to_string(ZPOOL_CONFIG_POOL_STATE)
+}
+
+pub fn zpool_config_pool_txg() -> String {
+    utf8_to_string(ZPOOL_CONFIG_POOL_TXG)
+}
+
+pub fn zpool_config_load_info() -> String {
+    utf8_to_string(ZPOOL_CONFIG_LOAD_INFO)
+}
+
+pub fn zpool_config_mmp_state() -> String {
+    utf8_to_string(ZPOOL_CONFIG_MMP_STATE)
+}
+
+pub fn zpool_config_mmp_hostname() -> String {
+    utf8_to_string(ZPOOL_CONFIG_MMP_HOSTNAME)
+}
+
+pub fn zpool_config_mmp_hostid() -> String {
+    utf8_to_string(ZPOOL_CONFIG_MMP_HOSTID)
+}
+
 pub fn zprop_value() -> String {
     utf8_to_string(ZPROP_VALUE)
 }
@@ -93,6 +184,14 @@ pub fn zpool_config_vdev_stats() -> String {
     utf8_to_string(ZPOOL_CONFIG_VDEV_STATS)
 }
 
+pub fn zpool_config_scan_stats() -> String {
+    utf8_to_string(ZPOOL_CONFIG_SCAN_STATS)
+}
+
+pub fn zpool_config_removing_stats() -> String {
+    utf8_to_string(ZPOOL_CONFIG_REMOVING_STATS)
+}
+
 pub fn zfs_type_dataset() -> zfs_type_t {
     zfs_type_t::ZFS_TYPE_FILESYSTEM | zfs_type_t::ZFS_TYPE_VOLUME | zfs_type_t::ZFS_TYPE_SNAPSHOT
 }
@@ -116,6 +215,20 @@ pub fn to_vdev_stat(mut xs: Vec<u64>) -> vdev_stat_t {
     unsafe { std::ptr::read(xs.as_ptr() as *const _) }
 }
 
+/// Converts a `Vec<u64>` to `pool_scan_stat_t`
+pub fn to_pool_scan_stat(mut xs: Vec<u64>) -> pool_scan_stat_t {
+    xs.resize(std::mem::size_of::<pool_scan_stat_t>() / 8, 0);
+
+    unsafe { std::ptr::read(xs.as_ptr() as *const _) }
+}
+
+/// Converts a `Vec<u64>` to `pool_removal_stat_t`
+pub fn to_pool_removal_stat(mut xs: Vec<u64>) -> pool_removal_stat_t {
+    xs.resize(std::mem::size_of::<pool_removal_stat_t>() / 8, 0);
+
+    unsafe { std::ptr::read(xs.as_ptr() as *const _) }
+}
+
 /// Converts a `u32` to `Option<vdev_state_t>`
 pub fn to_vdev_state(n: u32) -> Option<vdev_state_t> {
     if n <= 7 {
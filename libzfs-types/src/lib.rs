@@ -4,11 +4,21 @@
 
 //! libzfs-types — Shared types for libzfs
 //!
+extern crate serde;
 extern crate serde_derive;
 
 use serde_derive::{Deserialize, Serialize};
 
-use std::{error, ffi::IntoStringError, fmt, io::Error, path::PathBuf, result};
+pub mod nvlist;
+
+use std::{
+    error,
+    ffi::IntoStringError,
+    fmt,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    result,
+};
 
 #[derive(Debug)]
 pub enum LibZfsError {
@@ -16,6 +26,20 @@ pub enum LibZfsError {
     IntoString(IntoStringError),
     PoolNotFound(Option<String>, Option<u64>),
     ZfsNotFound(String),
+    Zfs(ZfsErrorKind, String),
+    ImportRefused(String, ImportSafety),
+    /// `SnapshotError::Failed`, each snapshot name with its error.
+    SnapshotsFailed(Vec<(String, LibZfsError)>),
+}
+
+fn fmt_snapshot_errors(f: &mut fmt::Formatter, errs: &[(String, LibZfsError)]) -> fmt::Result {
+    write!(f, "could not create snapshots")?;
+
+    for (i, (name, err)) in errs.iter().enumerate() {
+        write!(f, "{} {}: {}", if i == 0 { ":" } else { "," }, name, err)?;
+    }
+
+    Ok(())
 }
 
 impl fmt::Display for LibZfsError {
@@ -36,6 +60,25 @@ impl fmt::Display for LibZfsError {
             LibZfsError::ZfsNotFound(ref err) => {
                 write!(f, "The zfs object {} could not be found", err)
             }
+            LibZfsError::Zfs(_, ref description) => write!(f, "{}", description),
+            LibZfsError::ImportRefused(ref pool, ref safety) => match *safety {
+                ImportSafety::ActiveOnOtherHost {
+                    ref hostname,
+                    hostid,
+                } => write!(
+                    f,
+                    "The pool: {} is imported on host: {} (hostid: {:#x}).",
+                    pool, hostname, hostid
+                ),
+                ImportSafety::NoLocalHostid => write!(
+                    f,
+                    "The pool: {} has multihost enabled, but this host has no hostid.",
+                    pool
+                ),
+                ImportSafety::Destroyed => write!(f, "The pool: {} was destroyed.", pool),
+                _ => write!(f, "The pool: {} is not safe to import.", pool),
+            },
+            LibZfsError::SnapshotsFailed(ref errs) => fmt_snapshot_errors(f, errs),
         }
     }
 }
@@ -47,6 +90,11 @@ impl error::Error for LibZfsError {
             LibZfsError::IntoString(ref err) => Some(err),
             LibZfsError::PoolNotFound(_, _) => None,
             LibZfsError::ZfsNotFound(_) => None,
+            LibZfsError::Zfs(_, _) => None,
+            LibZfsError::ImportRefused(_, _) => None,
+            LibZfsError::SnapshotsFailed(ref errs) => {
+                errs.first().map(|(_, err)| err as &dyn error::Error)
+            }
         }
     }
 }
@@ -63,48 +111,645 @@ impl From<IntoStringError> for LibZfsError {
     }
 }
 
+impl serde::ser::Error for LibZfsError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        LibZfsError::Io(Error::new(ErrorKind::InvalidData, msg.to_string()))
+    }
+}
+
+impl serde::de::Error for LibZfsError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        LibZfsError::Io(Error::new(ErrorKind::InvalidData, msg.to_string()))
+    }
+}
+
 pub type Result<T> = result::Result<T, LibZfsError>;
 
+/// The error returned when a pool could not be exported.
+///
+/// Busy failures are split out so callers can decide
+/// whether to retry with a forced export.
+#[derive(Debug)]
+pub enum ExportError {
+    /// The pool, or one of its datasets, is still in use.
+    Busy(LibZfsError),
+    Other(LibZfsError),
+}
+
+impl ExportError {
+    pub fn is_busy(&self) -> bool {
+        match *self {
+            ExportError::Busy(_) => true,
+            ExportError::Other(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExportError::Busy(ref err) => write!(f, "{}", err),
+            ExportError::Other(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for ExportError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            ExportError::Busy(ref err) => Some(err),
+            ExportError::Other(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<ExportError> for LibZfsError {
+    fn from(err: ExportError) -> Self {
+        match err {
+            ExportError::Busy(err) => err,
+            ExportError::Other(err) => err,
+        }
+    }
+}
+
+pub type ExportResult = result::Result<(), ExportError>;
+
+/// The error returned when a device could not be attached,
+/// detached or replaced.
+#[derive(Debug)]
+pub enum AttachError {
+    /// The new device is smaller than the device it would mirror or replace.
+    DeviceTooSmall(LibZfsError),
+    /// The new device needs a larger sector size (ashift)
+    /// than the top-level vdev it would join.
+    SectorSizeMismatch(LibZfsError),
+    /// The change would leave no valid replicas of the data.
+    NoValidReplicas(LibZfsError),
+    /// The existing device cannot be attached to, detached or replaced,
+    /// for example because it is not part of a mirror.
+    BadTarget(LibZfsError),
+    Other(LibZfsError),
+}
+
+impl AttachError {
+    fn inner(&self) -> &LibZfsError {
+        match *self {
+            AttachError::DeviceTooSmall(ref err)
+            | AttachError::SectorSizeMismatch(ref err)
+            | AttachError::NoValidReplicas(ref err)
+            | AttachError::BadTarget(ref err)
+            | AttachError::Other(ref err) => err,
+        }
+    }
+}
+
+impl fmt::Display for AttachError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.inner())
+    }
+}
+
+impl error::Error for AttachError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        Some(self.inner())
+    }
+}
+
+impl From<AttachError> for LibZfsError {
+    fn from(err: AttachError) -> Self {
+        match err {
+            AttachError::DeviceTooSmall(err)
+            | AttachError::SectorSizeMismatch(err)
+            | AttachError::NoValidReplicas(err)
+            | AttachError::BadTarget(err)
+            | AttachError::Other(err) => err,
+        }
+    }
+}
+
+pub type AttachResult = result::Result<(), AttachError>;
+
+/// The error returned when snapshots could not be created.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// An atomic batch failed, so no snapshot in it was created.
+    /// Holds each snapshot name that caused the failure with its error.
+    Failed(Vec<(String, LibZfsError)>),
+    Other(LibZfsError),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::Failed(ref errs) => fmt_snapshot_errors(f, errs),
+            SnapshotError::Other(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            SnapshotError::Failed(ref errs) => {
+                errs.first().map(|(_, err)| err as &dyn error::Error)
+            }
+            SnapshotError::Other(ref err) => Some(err),
+        }
+    }
+}
+
+impl From<SnapshotError> for LibZfsError {
+    fn from(err: SnapshotError) -> Self {
+        match err {
+            SnapshotError::Failed(errs) => LibZfsError::SnapshotsFailed(errs),
+            SnapshotError::Other(err) => err,
+        }
+    }
+}
+
+pub type SnapshotResult<T> = result::Result<T, SnapshotError>;
+
+/// Whether a pool found by an import search can be imported
+/// without risking it being imported on two hosts at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportSafety {
+    /// The pool was last imported by this host and not exported,
+    /// for example after a crash.
+    SafeToImport,
+    /// The pool was cleanly exported and may be imported by any host.
+    PreviouslyExported,
+    /// The pool was destroyed. Like `zpool import -D`, importing it
+    /// needs a forced import.
+    Destroyed,
+    /// The pool is imported on another host. Either multihost (MMP)
+    /// saw recent activity, or the pool was last imported by that host
+    /// and never exported.
+    ActiveOnOtherHost { hostname: String, hostid: u64 },
+    /// The pool has multihost enabled, but this host has no hostid
+    /// so activity could not be checked.
+    NoLocalHostid,
+}
+
+impl ImportSafety {
+    pub fn is_safe(&self) -> bool {
+        *self == ImportSafety::SafeToImport || *self == ImportSafety::PreviouslyExported
+    }
+}
+
+/// The problem libzfs reports for a pool, as shown by `zpool status`
+/// and `zpool import`. `Ok` means no problem was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZpoolStatus {
+    CorruptCache,
+    MissingDevR,
+    MissingDevNr,
+    CorruptLabelR,
+    CorruptLabelNr,
+    BadGuidSum,
+    CorruptPool,
+    CorruptData,
+    FailingDev,
+    VersionNewer,
+    HostidMismatch,
+    HostidActive,
+    HostidRequired,
+    IoFailureWait,
+    IoFailureContinue,
+    IoFailureMmp,
+    BadLog,
+    Errata,
+    UnsupFeatRead,
+    UnsupFeatWrite,
+    FaultedDevR,
+    FaultedDevNr,
+    VersionOlder,
+    FeatDisabled,
+    Resilvering,
+    OfflineDev,
+    RemovedDev,
+    Ok,
+    Unknown,
+}
+
+/// The kind of error libzfs recorded on its handle
+/// after a failed operation.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum ZfsErrorKind {
+    NoMem,
+    BadProp,
+    PropReadonly,
+    PropType,
+    PropNonInherit,
+    PropSpace,
+    BadType,
+    Busy,
+    Exists,
+    NoEnt,
+    BadStream,
+    DsReadonly,
+    VolTooBig,
+    InvalidName,
+    BadRestore,
+    BadBackup,
+    BadTarget,
+    NoDevice,
+    BadDev,
+    NoReplicas,
+    Resilvering,
+    BadVersion,
+    PoolUnavail,
+    DevOverflow,
+    BadPath,
+    CrossTarget,
+    Zoned,
+    MountFailed,
+    UmountFailed,
+    UnshareNfsFailed,
+    ShareNfsFailed,
+    Perm,
+    NoSpc,
+    Fault,
+    Io,
+    Intr,
+    IsSpare,
+    InvalConfig,
+    Recursive,
+    NoHistory,
+    PoolProps,
+    PoolNotSup,
+    PoolInvalArg,
+    NameTooLong,
+    OpenFailed,
+    NoCap,
+    LabelFailed,
+    BadWho,
+    BadPerm,
+    BadPermSet,
+    NoDelegation,
+    UnshareSmbFailed,
+    ShareSmbFailed,
+    BadCache,
+    IsL2Cache,
+    VdevNotSup,
+    NotSup,
+    ActiveSpare,
+    UnplayedLogs,
+    ReftagRele,
+    ReftagHold,
+    TagTooLong,
+    PipeFailed,
+    ThreadCreateFailed,
+    PostSplitOnline,
+    Scrubbing,
+    NoScrub,
+    Diff,
+    DiffData,
+    PoolReadonly,
+    ScrubPaused,
+    ActivePool,
+    Unknown,
+}
+
+/// Counts of I/O operations or bytes, by ZIO type.
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, PartialOrd, Ord)]
+pub struct ZioTypeCounts {
+    pub null: u64,
+    pub read: u64,
+    pub write: u64,
+    pub free: u64,
+    pub claim: u64,
+    pub ioctl: u64,
+}
+
+/// Statistics libzfs keeps for each vdev, as reported by `zpool iostat`
+/// and `zpool status`.
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, PartialOrd, Ord)]
+pub struct VDevStats {
+    /// Time the stats were collected, in nanoseconds since boot.
+    pub timestamp: u64,
+    /// Bytes allocated.
+    pub alloc: u64,
+    /// Total capacity in bytes.
+    pub space: u64,
+    /// Deflated capacity in bytes.
+    pub dspace: u64,
+    /// Replaceable device size in bytes.
+    pub rsize: u64,
+    /// Expandable device size in bytes.
+    pub esize: u64,
+    pub ops: ZioTypeCounts,
+    pub bytes: ZioTypeCounts,
+    pub read_errors: u64,
+    pub write_errors: u64,
+    pub checksum_errors: u64,
+    /// Bytes repaired by self-healing.
+    pub self_healed: u64,
+    /// Whether the vdev is being removed. Always 0 on ZFS 0.7,
+    /// which removes devices synchronously.
+    pub scan_removing: u64,
+    /// Bytes scrubbed or resilvered on this vdev in the current scan.
+    pub scan_processed: u64,
+    /// Free space fragmentation, as a percentage.
+    pub fragmentation: u64,
+}
+
+/// The allocation class of a top-level vdev.
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Copy, PartialOrd, Ord)]
+pub enum AllocationClass {
+    /// A separate intent log (SLOG).
+    Log,
+    /// Holds metadata and, optionally, small blocks.
+    Special,
+    /// Holds the dedup table.
+    Dedup,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone, PartialOrd, Ord)]
 pub enum VDev {
     Mirror {
         children: Vec<VDev>,
         is_log: Option<bool>,
+        #[serde(default)]
+        alloc_class: Option<AllocationClass>,
+        #[serde(default)]
+        ashift: Option<u64>,
+        #[serde(default)]
+        stats: Option<VDevStats>,
     },
     RaidZ {
         children: Vec<VDev>,
+        #[serde(default)]
+        nparity: u64,
+        #[serde(default)]
+        alloc_class: Option<AllocationClass>,
+        #[serde(default)]
+        ashift: Option<u64>,
+        #[serde(default)]
+        stats: Option<VDevStats>,
+    },
+    /// A distributed spare RAID vdev, from pools created by later ZFS versions.
+    DRaid {
+        children: Vec<VDev>,
+        nparity: u64,
+        ndata: u64,
+        nspares: u64,
+        ngroups: u64,
+        alloc_class: Option<AllocationClass>,
+        ashift: Option<u64>,
+        stats: Option<VDevStats>,
     },
     Replacing {
         children: Vec<VDev>,
+        /// Index in `children` of the new device, while a resilver
+        /// onto it is running.
+        #[serde(default)]
+        new_device: Option<usize>,
+        #[serde(default)]
+        stats: Option<VDevStats>,
+    },
+    /// A hot spare standing in for a failed device.
+    Spare {
+        children: Vec<VDev>,
+        /// Index in `children` of the hot spare, whether or not
+        /// its resilver has finished.
+        new_device: Option<usize>,
+        stats: Option<VDevStats>,
     },
     Root {
         children: Vec<VDev>,
         spares: Vec<VDev>,
         cache: Vec<VDev>,
+        #[serde(default)]
+        stats: Option<VDevStats>,
     },
     Disk {
         guid: Option<u64>,
+        /// `"UNKNOWN"` when the config has no `vdev_stats`,
+        /// as in a cachefile or a label.
         state: String,
         path: PathBuf,
         dev_id: Option<String>,
         phys_path: Option<String>,
         whole_disk: Option<bool>,
         is_log: Option<bool>,
+        #[serde(default)]
+        alloc_class: Option<AllocationClass>,
+        #[serde(default)]
+        ashift: Option<u64>,
+        #[serde(default)]
+        stats: Option<VDevStats>,
     },
     File {
         guid: Option<u64>,
         state: String,
         path: PathBuf,
         is_log: Option<bool>,
+        #[serde(default)]
+        alloc_class: Option<AllocationClass>,
+        #[serde(default)]
+        ashift: Option<u64>,
+        #[serde(default)]
+        stats: Option<VDevStats>,
+    },
+    /// A spare built into a dRAID vdev.
+    DistributedSpare {
+        guid: Option<u64>,
+        state: String,
+        name: String,
+        stats: Option<VDevStats>,
+    },
+    /// A device the pool config expects but that could not be found.
+    Missing { guid: Option<u64> },
+    /// A placeholder for a log device that was removed.
+    Hole,
+    /// A removed top-level vdev whose blocks were remapped to other vdevs.
+    Indirect {
+        guid: Option<u64>,
+        stats: Option<VDevStats>,
     },
 }
 
+/// The state of a vdev, as reported by `zpool status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VDevState {
+    Unknown,
+    Closed,
+    Offline,
+    Removed,
+    CantOpen,
+    Faulted,
+    Degraded,
+    Healthy,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Deserialize, Serialize, Clone, PartialOrd, Ord)]
 pub struct ZProp {
     pub name: String,
     pub value: String,
 }
 
+/// The kind of scan a pool is running, or last ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanFunction {
+    None,
+    Scrub,
+    Resilver,
+}
+
+/// The state of a pool scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanState {
+    None,
+    Scanning,
+    Finished,
+    Canceled,
+}
+
+/// Progress of the current or last scan (scrub or resilver) of a pool.
+///
+/// Times are seconds since the epoch, sizes are in bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanStats {
+    pub function: ScanFunction,
+    pub state: ScanState,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub to_examine: u64,
+    pub examined: u64,
+    pub to_process: u64,
+    pub processed: u64,
+    pub errors: u64,
+    /// Bytes examined since the current pass started.
+    pub pass_examined: u64,
+    pub pass_start: u64,
+    /// When the scrub was paused, or 0 if it is not paused.
+    pub pass_scrub_pause: u64,
+    /// Total time the current pass has spent paused.
+    pub pass_scrub_spent_paused: u64,
+    /// Progress of a running scan, as of when these stats were read.
+    pub progress: Option<ScanProgress>,
+}
+
+impl ScanStats {
+    pub fn is_paused(&self) -> bool {
+        self.state == ScanState::Scanning && self.pass_scrub_pause != 0
+    }
+    /// Computes the progress of a running scan at `now`,
+    /// in seconds since the epoch, the same way `zpool status` does.
+    ///
+    /// Returns `None` if no scan is running.
+    pub fn progress_at(&self, now: u64) -> Option<ScanProgress> {
+        if self.state != ScanState::Scanning {
+            return None;
+        }
+
+        let issued = self.examined;
+        let remaining = self.to_examine.saturating_sub(issued);
+
+        let elapsed = now
+            .saturating_sub(self.pass_start)
+            .saturating_sub(self.pass_scrub_spent_paused)
+            .max(1);
+
+        let rate = self.pass_examined / elapsed;
+
+        let seconds_left = if self.is_paused() || rate == 0 {
+            None
+        } else {
+            Some(remaining / rate)
+        };
+
+        Some(ScanProgress {
+            issued,
+            remaining,
+            rate,
+            seconds_left,
+        })
+    }
+}
+
+/// How far a running scrub or resilver has got.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanProgress {
+    /// Bytes issued so far. ZFS 0.7 issues I/O as it examines
+    /// blocks, so this is the same as `ScanStats::examined`.
+    pub issued: u64,
+    /// Bytes still to process.
+    pub remaining: u64,
+    /// Bytes per second over the current pass.
+    pub rate: u64,
+    /// Estimated seconds until the scan completes.
+    /// `None` while paused, or before any progress has been made.
+    pub seconds_left: Option<u64>,
+}
+
+/// Progress of the current or last removal of a top-level vdev,
+/// for ZFS versions that evacuate removed vdevs in the background.
+///
+/// Times are seconds since the epoch, sizes are in bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemovalStats {
+    pub state: ScanState,
+    /// Guid of the vdev being removed.
+    pub removing_vdev: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub to_copy: u64,
+    pub copied: u64,
+    /// Memory used to map blocks of removed vdevs to their new location.
+    pub mapping_memory: u64,
+}
+
+/// One of the four labels ZFS writes to every vdev,
+/// two at the start of the device and two at the end.
+///
+/// Fields are `None` when the label, or that entry in it, is missing.
+#[derive(Debug, Serialize, PartialEq, Deserialize, Clone)]
+pub struct VDevLabel {
+    /// Which label this is, 0 to 3
+    pub index: usize,
+    /// Byte offset of the label on the device
+    pub offset: u64,
+    pub pool_name: Option<String>,
+    pub pool_guid: Option<u64>,
+    /// The guid of the vdev the label was written to
+    pub guid: Option<u64>,
+    pub txg: Option<u64>,
+    pub hostid: Option<u64>,
+    pub hostname: Option<String>,
+    /// The pool state name, e.g. `ACTIVE` or `EXPORTED`
+    pub state: Option<String>,
+    /// The full label config, if it could be decoded
+    pub config: Option<nvlist::NvList>,
+    /// The valid uberblocks in this label's ring
+    pub uberblocks: Vec<Uberblock>,
+}
+
+impl VDevLabel {
+    /// Whether a config could be read from this label
+    pub fn is_valid(&self) -> bool {
+        self.config.is_some()
+    }
+    /// The uberblock with the highest txg, which ZFS would use
+    pub fn active_uberblock(&self) -> Option<&Uberblock> {
+        self.uberblocks
+            .iter()
+            .max_by_key(|ub| (ub.txg, ub.timestamp))
+    }
+}
+
+/// An uberblock from a label's uberblock ring.
+#[derive(Debug, Serialize, PartialEq, Deserialize, Clone)]
+pub struct Uberblock {
+    /// The slot in the ring this uberblock was read from
+    pub slot: usize,
+    pub version: u64,
+    pub txg: u64,
+    pub guid_sum: u64,
+    /// Seconds since the epoch
+    pub timestamp: u64,
+}
+
 /// A Pool at a point in time
 #[derive(Debug, Serialize, PartialEq, Deserialize, Clone)]
 pub struct Pool {
@@ -119,6 +764,8 @@ pub struct Pool {
     pub vdev: VDev,
     pub props: Vec<ZProp>,
     pub datasets: Vec<Dataset>,
+    #[serde(default)]
+    pub scan: Option<ScanStats>,
 }
 
 /// A Dataset at a point in time
@@ -128,4 +775,138 @@ pub struct Dataset {
     pub guid: String,
     pub kind: String,
     pub props: Vec<ZProp>,
+    /// Oldest first
+    #[serde(default)]
+    pub snapshots: Vec<Snapshot>,
+}
+
+/// A Dataset and the filesystems and volumes below it, at a point in time
+#[derive(Debug, Serialize, PartialEq, Deserialize, Clone)]
+pub struct DatasetTree {
+    pub dataset: Dataset,
+    pub children: Vec<DatasetTree>,
+}
+
+impl DatasetTree {
+    /// Nests a flat list of datasets by name.
+    ///
+    /// Datasets whose parent is not in the list become roots.
+    /// Siblings are ordered by name.
+    pub fn from_datasets<I: IntoIterator<Item = Dataset>>(xs: I) -> Vec<DatasetTree> {
+        fn insert(trees: &mut Vec<DatasetTree>, dataset: Dataset) {
+            let parent = trees.iter_mut().find(|t| {
+                dataset.name.starts_with(&t.dataset.name)
+                    && dataset.name[t.dataset.name.len()..].starts_with('/')
+            });
+
+            match parent {
+                Some(t) => insert(&mut t.children, dataset),
+                None => trees.push(DatasetTree {
+                    dataset,
+                    children: vec![],
+                }),
+            }
+        }
+
+        let mut xs: Vec<Dataset> = xs.into_iter().collect();
+        xs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut trees = vec![];
+
+        for x in xs {
+            insert(&mut trees, x);
+        }
+
+        trees
+    }
+    /// Finds the tree rooted at the dataset called `name`.
+    pub fn find(&self, name: &str) -> Option<&DatasetTree> {
+        if self.dataset.name == name {
+            Some(self)
+        } else {
+            self.children.iter().filter_map(|x| x.find(name)).next()
+        }
+    }
+}
+
+/// What a rollback destroyed to reach its target snapshot
+#[derive(Debug, Default, Serialize, PartialEq, Deserialize, Clone)]
+pub struct RollbackReport {
+    /// Snapshots newer than the target, oldest first
+    pub snapshots: Vec<String>,
+    /// Bookmarks newer than the target
+    pub bookmarks: Vec<String>,
+    /// Clones of the destroyed snapshots, and everything below them
+    pub clones: Vec<String>,
+}
+
+/// A Snapshot at a point in time
+#[derive(Debug, Serialize, PartialEq, Deserialize, Clone)]
+pub struct Snapshot {
+    pub name: String,
+    /// Seconds since the epoch
+    pub creation: u64,
+    /// Bytes
+    pub used: u64,
+    /// Bytes
+    pub referenced: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataset(name: &str) -> Dataset {
+        Dataset {
+            name: name.to_string(),
+            guid: "0".to_string(),
+            kind: "filesystem".to_string(),
+            props: vec![],
+            snapshots: vec![],
+        }
+    }
+
+    #[test]
+    fn nest_datasets_by_name() {
+        let trees = DatasetTree::from_datasets(vec![
+            dataset("pool/lustre/ost0"),
+            dataset("pool/lustre-old"),
+            dataset("pool"),
+            dataset("pool/lustre"),
+            dataset("other/ds"),
+        ]);
+
+        assert_eq!(trees.len(), 2);
+        assert_eq!(trees[0].dataset.name, "other/ds");
+
+        let pool = &trees[1];
+        let names: Vec<_> = pool.children.iter().map(|x| &x.dataset.name).collect();
+        assert_eq!(names, vec!["pool/lustre", "pool/lustre-old"]);
+
+        let ost0 = pool.find("pool/lustre/ost0").expect("could not find ost0");
+        assert!(ost0.children.is_empty());
+        assert!(pool.find("pool/lustre/ost1").is_none());
+    }
+
+    #[test]
+    fn snapshot_errors_survive_conversion() {
+        let err = SnapshotError::Failed(vec![(
+            "pool/ds@snap".to_string(),
+            LibZfsError::Zfs(ZfsErrorKind::Exists, "dataset already exists".to_string()),
+        )]);
+        let msg = err.to_string();
+
+        let err = LibZfsError::from(err);
+        assert_eq!(err.to_string(), msg);
+
+        match err {
+            LibZfsError::SnapshotsFailed(ref errs) => match errs[..] {
+                [(ref name, LibZfsError::Zfs(ZfsErrorKind::Exists, _))] => {
+                    assert_eq!(name, "pool/ds@snap")
+                }
+                _ => panic!("lost the snapshot error: {:?}", errs),
+            },
+            _ => panic!("expected SnapshotsFailed, got {:?}", err),
+        }
+    }
 }
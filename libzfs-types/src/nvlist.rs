@@ -0,0 +1,587 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! A plain, owned nvlist and a pure-Rust codec for the XDR wire format.
+//!
+//! This mirrors what `libzfs::nvpair` reads through libnvpair, so packed
+//! configs can be parsed (and produced) on machines without ZFS installed.
+//! Buffers must use `NV_ENCODE_XDR`, as produced by
+//! `NvListRef::pack(NvEncoding::Xdr)` or `zpool.cache`.
+
+use serde_derive::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use {LibZfsError, Result};
+
+/// The only nvlist version in use
+pub const NV_VERSION: i32 = 0;
+/// List flag: adding a pair replaces any pair with the same name
+pub const NV_UNIQUE_NAME: u32 = 0x1;
+/// List flag: adding a pair replaces any pair with the same name and type
+pub const NV_UNIQUE_NAME_TYPE: u32 = 0x2;
+
+const NV_ENCODE_XDR: u8 = 1;
+
+/// An owned nvlist: its flags plus its pairs in encoding order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NvList {
+    pub version: i32,
+    pub flags: u32,
+    pub pairs: Vec<NvPair>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NvPair {
+    pub name: String,
+    pub value: NvValue,
+}
+
+/// An owned nvpair value, one variant per `data_type_t`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NvValue {
+    /// A name-only flag (`DATA_TYPE_BOOLEAN`); presence is the value
+    Boolean,
+    BooleanValue(bool),
+    Byte(u8),
+    Int8(i8),
+    UInt8(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    HrTime(i64),
+    String(String),
+    NvList(NvList),
+    BooleanArray(Vec<bool>),
+    ByteArray(Vec<u8>),
+    Int8Array(Vec<i8>),
+    UInt8Array(Vec<u8>),
+    Int16Array(Vec<i16>),
+    UInt16Array(Vec<u16>),
+    Int32Array(Vec<i32>),
+    UInt32Array(Vec<u32>),
+    Int64Array(Vec<i64>),
+    UInt64Array(Vec<u64>),
+    StringArray(Vec<String>),
+    NvListArray(Vec<NvList>),
+}
+
+impl Default for NvList {
+    fn default() -> Self {
+        NvList::new(NV_UNIQUE_NAME)
+    }
+}
+
+impl NvList {
+    /// Create an empty list with the given `NV_UNIQUE_*` flags
+    pub fn new(flags: u32) -> Self {
+        NvList {
+            version: NV_VERSION,
+            flags,
+            pairs: vec![],
+        }
+    }
+
+    /// Decode a packed XDR buffer.
+    pub fn from_xdr(buf: &[u8]) -> Result<Self> {
+        if buf.len() < 4 {
+            return Err(invalid("nvlist buffer is too short for a header"));
+        }
+
+        if buf[0] != NV_ENCODE_XDR {
+            return Err(invalid(format!(
+                "unsupported nvlist encoding {}, only XDR can be decoded",
+                buf[0]
+            )));
+        }
+
+        let mut r = Reader { buf, pos: 4 };
+        r.nv_list()
+    }
+
+    /// Encode this list into a packed XDR buffer,
+    /// byte-for-byte what `nvlist_pack` produces.
+    pub fn to_xdr(&self) -> Result<Vec<u8>> {
+        let endian = if cfg!(target_endian = "little") { 1 } else { 0 };
+        let mut buf = vec![NV_ENCODE_XDR, endian, 0, 0];
+        write_nv_list(&mut buf, self)?;
+
+        Ok(buf)
+    }
+
+    /// The first pair called `name`
+    pub fn get(&self, name: &str) -> Option<&NvValue> {
+        self.pairs.iter().find(|p| p.name == name).map(|p| &p.value)
+    }
+
+    /// Add a pair, honouring this list's uniqueness flags
+    pub fn insert<S: Into<String>>(&mut self, name: S, value: NvValue) {
+        let name = name.into();
+
+        if self.flags & NV_UNIQUE_NAME != 0 {
+            self.pairs.retain(|p| p.name != name);
+        } else if self.flags & NV_UNIQUE_NAME_TYPE != 0 {
+            self.pairs
+                .retain(|p| p.name != name || p.value.data_type() != value.data_type());
+        }
+
+        self.pairs.push(NvPair { name, value });
+    }
+}
+
+impl NvValue {
+    /// The numeric `data_type_t` of this value
+    pub fn data_type(&self) -> i32 {
+        match *self {
+            NvValue::Boolean => 1,
+            NvValue::Byte(_) => 2,
+            NvValue::Int16(_) => 3,
+            NvValue::UInt16(_) => 4,
+            NvValue::Int32(_) => 5,
+            NvValue::UInt32(_) => 6,
+            NvValue::Int64(_) => 7,
+            NvValue::UInt64(_) => 8,
+            NvValue::String(_) => 9,
+            NvValue::ByteArray(_) => 10,
+            NvValue::Int16Array(_) => 11,
+            NvValue::UInt16Array(_) => 12,
+            NvValue::Int32Array(_) => 13,
+            NvValue::UInt32Array(_) => 14,
+            NvValue::Int64Array(_) => 15,
+            NvValue::UInt64Array(_) => 16,
+            NvValue::StringArray(_) => 17,
+            NvValue::HrTime(_) => 18,
+            NvValue::NvList(_) => 19,
+            NvValue::NvListArray(_) => 20,
+            NvValue::BooleanValue(_) => 21,
+            NvValue::Int8(_) => 22,
+            NvValue::UInt8(_) => 23,
+            NvValue::BooleanArray(_) => 24,
+            NvValue::Int8Array(_) => 25,
+            NvValue::UInt8Array(_) => 26,
+            NvValue::Double(_) => 27,
+        }
+    }
+
+    fn nelem(&self) -> usize {
+        match *self {
+            NvValue::Boolean => 0,
+            NvValue::BooleanArray(ref xs) => xs.len(),
+            NvValue::ByteArray(ref xs) | NvValue::UInt8Array(ref xs) => xs.len(),
+            NvValue::Int8Array(ref xs) => xs.len(),
+            NvValue::Int16Array(ref xs) => xs.len(),
+            NvValue::UInt16Array(ref xs) => xs.len(),
+            NvValue::Int32Array(ref xs) => xs.len(),
+            NvValue::UInt32Array(ref xs) => xs.len(),
+            NvValue::Int64Array(ref xs) => xs.len(),
+            NvValue::UInt64Array(ref xs) => xs.len(),
+            NvValue::StringArray(ref xs) => xs.len(),
+            NvValue::NvListArray(ref xs) => xs.len(),
+            _ => 1,
+        }
+    }
+
+    /// The size of the value inside a native `nvpair_t`,
+    /// as computed by `i_get_value_size`.
+    fn native_size(&self) -> usize {
+        // sizeof (nvlist_t), already 8 byte aligned
+        const NVLIST_SIZE: usize = 24;
+
+        match *self {
+            NvValue::Boolean => 0,
+            NvValue::BooleanValue(_) => 4,
+            NvValue::Byte(_) | NvValue::Int8(_) | NvValue::UInt8(_) => 1,
+            NvValue::Int16(_) | NvValue::UInt16(_) => 2,
+            NvValue::Int32(_) | NvValue::UInt32(_) => 4,
+            NvValue::Int64(_) | NvValue::UInt64(_) | NvValue::HrTime(_) | NvValue::Double(_) => 8,
+            NvValue::String(ref x) => x.len() + 1,
+            NvValue::NvList(_) => NVLIST_SIZE,
+            NvValue::BooleanArray(ref xs) => xs.len() * 4,
+            NvValue::ByteArray(ref xs) | NvValue::UInt8Array(ref xs) => xs.len(),
+            NvValue::Int8Array(ref xs) => xs.len(),
+            NvValue::Int16Array(ref xs) => xs.len() * 2,
+            NvValue::UInt16Array(ref xs) => xs.len() * 2,
+            NvValue::Int32Array(ref xs) => xs.len() * 4,
+            NvValue::UInt32Array(ref xs) => xs.len() * 4,
+            NvValue::Int64Array(ref xs) => xs.len() * 8,
+            NvValue::UInt64Array(ref xs) => xs.len() * 8,
+            NvValue::StringArray(ref xs) => xs.iter().map(|x| 8 + x.len() + 1).sum(),
+            NvValue::NvListArray(ref xs) => xs.len() * (8 + NVLIST_SIZE),
+        }
+    }
+}
+
+fn invalid<S: Into<String>>(msg: S) -> LibZfsError {
+    LibZfsError::Io(Error::new(ErrorKind::InvalidData, msg.into()))
+}
+
+fn align(x: usize, to: usize) -> usize {
+    (x + to - 1) & !(to - 1)
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() - self.pos < n {
+            return Err(invalid("unexpected end of nvlist buffer"));
+        }
+
+        let xs = &self.buf[self.pos..self.pos + n];
+        self.pos += n;
+
+        Ok(xs)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let xs = self.take(4)?;
+
+        Ok(xs.iter().fold(0, |acc, x| (acc << 8) | u32::from(*x)))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let hi = u64::from(self.u32()?);
+        let lo = u64::from(self.u32()?);
+
+        Ok((hi << 32) | lo)
+    }
+
+    fn opaque(&mut self, n: usize) -> Result<&'a [u8]> {
+        let xs = self.take(align(n, 4))?;
+
+        Ok(&xs[..n])
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let n = self.u32()? as usize;
+        let xs = self.opaque(n)?;
+
+        String::from_utf8(xs.to_vec()).map_err(|e| invalid(e.to_string()))
+    }
+
+    fn array<T, F>(&mut self, nelem: usize, f: F) -> Result<Vec<T>>
+    where
+        F: Fn(&mut Self) -> Result<T>,
+    {
+        // xdr_array repeats the element count
+        if self.u32()? as usize != nelem {
+            return Err(invalid(
+                "nvpair array length does not match its element count",
+            ));
+        }
+
+        self.times(nelem, f)
+    }
+
+    fn times<T, F>(&mut self, nelem: usize, f: F) -> Result<Vec<T>>
+    where
+        F: Fn(&mut Self) -> Result<T>,
+    {
+        // every element takes at least four bytes, so a bogus count
+        // fails here instead of attempting a huge allocation
+        if nelem > (self.buf.len() - self.pos) / 4 {
+            return Err(invalid("nvpair element count exceeds the buffer"));
+        }
+
+        (0..nelem).map(|_| f(self)).collect()
+    }
+
+    fn nv_list(&mut self) -> Result<NvList> {
+        let version = self.i32()?;
+        let flags = self.u32()?;
+        let mut pairs = vec![];
+
+        loop {
+            let start = self.pos;
+            let encode_size = self.u32()? as usize;
+            let decode_size = self.u32()?;
+
+            if encode_size == 0 && decode_size == 0 {
+                break;
+            }
+
+            let pair = self.nv_pair()?;
+
+            if self.pos - start != encode_size {
+                return Err(invalid(format!(
+                    "nvpair {} has a corrupt encoded size",
+                    pair.name
+                )));
+            }
+
+            pairs.push(pair);
+        }
+
+        Ok(NvList {
+            version,
+            flags,
+            pairs,
+        })
+    }
+
+    fn nv_pair(&mut self) -> Result<NvPair> {
+        let name = self.string()?;
+        let data_type = self.i32()?;
+        let nelem = self.u32()? as usize;
+
+        let value = match data_type {
+            1 => NvValue::Boolean,
+            2 => NvValue::Byte(self.u32()? as u8),
+            3 => NvValue::Int16(self.i32()? as i16),
+            4 => NvValue::UInt16(self.u32()? as u16),
+            5 => NvValue::Int32(self.i32()?),
+            6 => NvValue::UInt32(self.u32()?),
+            7 => NvValue::Int64(self.u64()? as i64),
+            8 => NvValue::UInt64(self.u64()?),
+            9 => NvValue::String(self.string()?),
+            10 => NvValue::ByteArray(self.opaque(nelem)?.to_vec()),
+            11 => NvValue::Int16Array(self.array(nelem, |r| Ok(r.i32()? as i16))?),
+            12 => NvValue::UInt16Array(self.array(nelem, |r| Ok(r.u32()? as u16))?),
+            13 => NvValue::Int32Array(self.array(nelem, |r| r.i32())?),
+            14 => NvValue::UInt32Array(self.array(nelem, |r| r.u32())?),
+            15 => NvValue::Int64Array(self.array(nelem, |r| Ok(r.u64()? as i64))?),
+            16 => NvValue::UInt64Array(self.array(nelem, |r| r.u64())?),
+            17 => NvValue::StringArray(self.times(nelem, |r| r.string())?),
+            18 => NvValue::HrTime(self.u64()? as i64),
+            19 => NvValue::NvList(self.nv_list()?),
+            20 => NvValue::NvListArray(self.times(nelem, |r| r.nv_list())?),
+            21 => NvValue::BooleanValue(self.u32()? != 0),
+            22 => NvValue::Int8(self.i32()? as i8),
+            23 => NvValue::UInt8(self.u32()? as u8),
+            24 => NvValue::BooleanArray(self.array(nelem, |r| Ok(r.u32()? != 0))?),
+            25 => NvValue::Int8Array(self.array(nelem, |r| Ok(r.i32()? as i8))?),
+            26 => NvValue::UInt8Array(self.array(nelem, |r| Ok(r.u32()? as u8))?),
+            27 => NvValue::Double(f64::from_bits(self.u64()?)),
+            x => {
+                return Err(invalid(format!(
+                    "nvpair {} has unknown data type {}",
+                    name, x
+                )))
+            }
+        };
+
+        Ok(NvPair { name, value })
+    }
+}
+
+fn put_u32(buf: &mut Vec<u8>, x: u32) {
+    buf.extend_from_slice(&[(x >> 24) as u8, (x >> 16) as u8, (x >> 8) as u8, x as u8]);
+}
+
+fn put_i32(buf: &mut Vec<u8>, x: i32) {
+    put_u32(buf, x as u32);
+}
+
+fn put_u64(buf: &mut Vec<u8>, x: u64) {
+    put_u32(buf, (x >> 32) as u32);
+    put_u32(buf, x as u32);
+}
+
+fn put_opaque(buf: &mut Vec<u8>, xs: &[u8]) {
+    buf.extend_from_slice(xs);
+    let pad = align(xs.len(), 4) - xs.len();
+    buf.extend_from_slice(&[0u8; 4][..pad]);
+}
+
+fn put_string(buf: &mut Vec<u8>, x: &str) -> Result<()> {
+    if x.as_bytes().contains(&0) {
+        return Err(invalid("nvlist strings cannot contain NUL bytes"));
+    }
+
+    put_u32(buf, x.len() as u32);
+    put_opaque(buf, x.as_bytes());
+
+    Ok(())
+}
+
+/// `xdr_char` widens through a (signed, on x86) `char`
+fn put_char(buf: &mut Vec<u8>, x: u8) {
+    put_i32(buf, i32::from(x as i8));
+}
+
+fn put_array<T, F>(buf: &mut Vec<u8>, xs: &[T], f: F)
+where
+    F: Fn(&mut Vec<u8>, &T),
+{
+    put_u32(buf, xs.len() as u32);
+    for x in xs {
+        f(buf, x);
+    }
+}
+
+fn write_nv_list(buf: &mut Vec<u8>, nvl: &NvList) -> Result<()> {
+    put_i32(buf, nvl.version);
+    put_u32(buf, nvl.flags);
+
+    for pair in &nvl.pairs {
+        let body = write_nv_pair(pair)?;
+
+        // encode size covers the two size words as well
+        put_u32(buf, (body.len() + 8) as u32);
+        put_u32(buf, native_pair_size(pair) as u32);
+        buf.extend_from_slice(&body);
+    }
+
+    put_u64(buf, 0);
+
+    Ok(())
+}
+
+/// The size of the pair in memory (`nvp_size`), which libnvpair
+/// checks when unpacking.
+fn native_pair_size(pair: &NvPair) -> usize {
+    // sizeof (nvpair_t)
+    const NVPAIR_SIZE: usize = 16;
+
+    align(NVPAIR_SIZE + pair.name.len() + 1, 8) + align(pair.value.native_size(), 8)
+}
+
+fn write_nv_pair(pair: &NvPair) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+
+    put_string(&mut buf, &pair.name)?;
+    put_i32(&mut buf, pair.value.data_type());
+    put_u32(&mut buf, pair.value.nelem() as u32);
+
+    {
+        let b = &mut buf;
+
+        match pair.value {
+            NvValue::Boolean => {}
+            NvValue::BooleanValue(x) => put_u32(b, x as u32),
+            NvValue::Byte(x) | NvValue::UInt8(x) => put_char(b, x),
+            NvValue::Int8(x) => put_i32(b, i32::from(x)),
+            NvValue::Int16(x) => put_i32(b, i32::from(x)),
+            NvValue::UInt16(x) => put_u32(b, u32::from(x)),
+            NvValue::Int32(x) => put_i32(b, x),
+            NvValue::UInt32(x) => put_u32(b, x),
+            NvValue::Int64(x) | NvValue::HrTime(x) => put_u64(b, x as u64),
+            NvValue::UInt64(x) => put_u64(b, x),
+            NvValue::Double(x) => put_u64(b, x.to_bits()),
+            NvValue::String(ref x) => put_string(b, x)?,
+            NvValue::NvList(ref x) => write_nv_list(b, x)?,
+            NvValue::BooleanArray(ref xs) => put_array(b, xs, |b, x| put_u32(b, *x as u32)),
+            NvValue::ByteArray(ref xs) => put_opaque(b, xs),
+            NvValue::Int8Array(ref xs) => put_array(b, xs, |b, x| put_i32(b, i32::from(*x))),
+            NvValue::UInt8Array(ref xs) => put_array(b, xs, |b, x| put_char(b, *x)),
+            NvValue::Int16Array(ref xs) => put_array(b, xs, |b, x| put_i32(b, i32::from(*x))),
+            NvValue::UInt16Array(ref xs) => put_array(b, xs, |b, x| put_u32(b, u32::from(*x))),
+            NvValue::Int32Array(ref xs) => put_array(b, xs, |b, x| put_i32(b, *x)),
+            NvValue::UInt32Array(ref xs) => put_array(b, xs, |b, x| put_u32(b, *x)),
+            NvValue::Int64Array(ref xs) => put_array(b, xs, |b, x| put_u64(b, *x as u64)),
+            NvValue::UInt64Array(ref xs) => put_array(b, xs, |b, x| put_u64(b, *x)),
+            NvValue::StringArray(ref xs) => {
+                for x in xs {
+                    put_string(b, x)?;
+                }
+            }
+            NvValue::NvListArray(ref xs) => {
+                for x in xs {
+                    write_nv_list(b, x)?;
+                }
+            }
+        };
+    }
+
+    if buf.len() > i32::MAX as usize {
+        return Err(invalid(format!(
+            "nvpair {} is too large to encode",
+            pair.name
+        )));
+    }
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // {"a": uint64 1} as packed by nvlist_pack(NV_ENCODE_XDR)
+    // on a little endian host
+    const SIMPLE: [u8; 52] = [
+        1, 1, 0, 0, // header
+        0, 0, 0, 0, 0, 0, 0, 1, // version, NV_UNIQUE_NAME
+        0, 0, 0, 32, 0, 0, 0, 32, // encode size, decode size
+        0, 0, 0, 1, b'a', 0, 0, 0, // name
+        0, 0, 0, 8, 0, 0, 0, 1, // DATA_TYPE_UINT64, nelem
+        0, 0, 0, 0, 0, 0, 0, 1, // value
+        0, 0, 0, 0, 0, 0, 0, 0, // end of list
+    ];
+
+    #[test]
+    fn decode_simple() {
+        let nvl = NvList::from_xdr(&SIMPLE).unwrap();
+
+        assert_eq!(nvl.flags, NV_UNIQUE_NAME);
+        assert_eq!(nvl.get("a"), Some(&NvValue::UInt64(1)));
+    }
+
+    #[test]
+    fn encode_simple() {
+        let mut nvl = NvList::default();
+        nvl.insert("a", NvValue::UInt64(1));
+
+        let buf = nvl.to_xdr().unwrap();
+
+        if cfg!(target_endian = "little") {
+            assert_eq!(&buf[..], &SIMPLE[..]);
+        }
+    }
+
+    #[test]
+    fn round_trip_all_types() {
+        let mut child = NvList::default();
+        child.insert("path", NvValue::String("/dev/sda".to_string()));
+
+        let mut nvl = NvList::default();
+        nvl.insert("flag", NvValue::Boolean);
+        nvl.insert("bool", NvValue::BooleanValue(true));
+        nvl.insert("byte", NvValue::Byte(0xff));
+        nvl.insert("i8", NvValue::Int8(-3));
+        nvl.insert("u8", NvValue::UInt8(200));
+        nvl.insert("i16", NvValue::Int16(-300));
+        nvl.insert("u16", NvValue::UInt16(60000));
+        nvl.insert("i32", NvValue::Int32(-70000));
+        nvl.insert("u32", NvValue::UInt32(4_000_000_000));
+        nvl.insert("i64", NvValue::Int64(-1));
+        nvl.insert("u64", NvValue::UInt64(u64::MAX));
+        nvl.insert("double", NvValue::Double(1.5));
+        nvl.insert("hrtime", NvValue::HrTime(123_456_789));
+        nvl.insert("nvlist", NvValue::NvList(child.clone()));
+        nvl.insert("bools", NvValue::BooleanArray(vec![true, false]));
+        nvl.insert("bytes", NvValue::ByteArray(vec![1, 2, 3, 4, 5]));
+        nvl.insert("i8s", NvValue::Int8Array(vec![-1, 1]));
+        nvl.insert("u8s", NvValue::UInt8Array(vec![0, 255]));
+        nvl.insert("i16s", NvValue::Int16Array(vec![-1, 1]));
+        nvl.insert("u16s", NvValue::UInt16Array(vec![0, 65535]));
+        nvl.insert("i32s", NvValue::Int32Array(vec![-1, 1]));
+        nvl.insert("u32s", NvValue::UInt32Array(vec![]));
+        nvl.insert("i64s", NvValue::Int64Array(vec![i64::MIN]));
+        nvl.insert("u64s", NvValue::UInt64Array(vec![1, 2, 3]));
+        nvl.insert(
+            "strings",
+            NvValue::StringArray(vec!["a".to_string(), "bcde".to_string()]),
+        );
+        nvl.insert("children", NvValue::NvListArray(vec![child.clone(), child]));
+
+        let buf = nvl.to_xdr().unwrap();
+
+        assert_eq!(NvList::from_xdr(&buf).unwrap(), nvl);
+    }
+
+    #[test]
+    fn reject_truncated() {
+        assert!(NvList::from_xdr(&SIMPLE[..40]).is_err());
+    }
+}
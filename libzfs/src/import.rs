@@ -0,0 +1,590 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Options for searching for and importing pools.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::nvlist as plain;
+use libzfs_types::{ImportSafety, Result, ZpoolStatus};
+use nvpair;
+use serde_derive::Serialize;
+use std::ffi::CStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::raw::c_int;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use vdev::{enumerate_vdev_tree, VDev};
+use zprop_list::ZProp;
+
+/// The default cachefile, where pools imported on this node are recorded.
+pub const ZPOOL_CACHE: &str = "/etc/zfs/zpool.cache";
+
+/// Restricts a search to a single pool.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportFilter {
+    Name(String),
+    Guid(u64),
+}
+
+/// Controls how `Libzfs` searches for importable pools
+/// and how the pools it finds are imported.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ImportOptions {
+    pub(crate) search_paths: Vec<PathBuf>,
+    pub(crate) cachefile: Option<PathBuf>,
+    pub(crate) filter: Option<ImportFilter>,
+    pub(crate) new_name: Option<String>,
+    pub(crate) readonly: bool,
+    pub(crate) altroot: Option<PathBuf>,
+    pub(crate) force: bool,
+    pub(crate) missing_log: bool,
+    pub(crate) props: Vec<ZProp>,
+}
+
+impl ImportFilter {
+    /// Whether the pool `config` is the one this filter asks for.
+    pub(crate) fn matches(&self, config: &nvpair::NvListRef) -> bool {
+        match *self {
+            ImportFilter::Name(ref name) => config
+                .lookup_string(sys::zpool_config_pool_name())
+                .map(|x| x.as_bytes() == name.as_bytes())
+                .unwrap_or(false),
+            ImportFilter::Guid(guid) => {
+                config.lookup_uint64(sys::zpool_config_pool_guid()).ok() == Some(guid)
+            }
+        }
+    }
+}
+
+impl ImportOptions {
+    pub fn new() -> ImportOptions {
+        ImportOptions::default()
+    }
+    /// Adds a directory to search for devices, like `zpool import -d`.
+    /// When no paths are given the default device directories are searched.
+    pub fn search_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.search_paths.push(path.into());
+        self
+    }
+    /// Reads pool configs from a cachefile instead of scanning devices,
+    /// like `zpool import -c`.
+    pub fn cachefile<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.cachefile = Some(path.into());
+        self
+    }
+    /// Only finds the pool with this name.
+    /// Replaces any previous `name` or `guid` filter.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.filter = Some(ImportFilter::Name(name.into()));
+        self
+    }
+    /// Only finds the pool with this guid.
+    /// Replaces any previous `name` or `guid` filter.
+    pub fn guid(mut self, guid: u64) -> Self {
+        self.filter = Some(ImportFilter::Guid(guid));
+        self
+    }
+    /// Imports the pool under a different name.
+    pub fn new_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.new_name = Some(name.into());
+        self
+    }
+    /// Imports the pool read-only.
+    pub fn readonly(mut self, readonly: bool) -> Self {
+        self.readonly = readonly;
+        self
+    }
+    /// Imports the pool with an alternate root, like `zpool import -R`.
+    /// Unless a cachefile is also given, the pool is not added to the cachefile.
+    pub fn altroot<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.altroot = Some(path.into());
+        self
+    }
+    /// Imports the pool even if it appears to be in use by another system.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+    /// Imports the pool even if its log devices are missing.
+    pub fn missing_log(mut self, missing_log: bool) -> Self {
+        self.missing_log = missing_log;
+        self
+    }
+    /// Sets a pool property at import time, like `zpool import -o`.
+    pub fn prop<S: Into<String>, T: Into<String>>(mut self, name: S, value: T) -> Self {
+        self.props.push(ZProp {
+            name: name.into(),
+            value: value.into(),
+        });
+        self
+    }
+    /// The `ZFS_IMPORT_*` flags for these options.
+    pub(crate) fn flags(&self) -> c_int {
+        let mut flags = sys::ZFS_IMPORT_NORMAL;
+
+        if self.force {
+            flags |= sys::ZFS_IMPORT_ANY_HOST;
+        }
+
+        if self.missing_log {
+            flags |= sys::ZFS_IMPORT_MISSING_LOG;
+        }
+
+        flags as c_int
+    }
+    /// The pool properties to set at import time, including
+    /// those implied by `readonly` and `altroot`.
+    pub(crate) fn import_props(&self) -> Vec<ZProp> {
+        let mut props = self.props.clone();
+
+        if self.readonly {
+            props.push(ZProp {
+                name: "readonly".to_string(),
+                value: "on".to_string(),
+            });
+        }
+
+        if let Some(ref altroot) = self.altroot {
+            props.push(ZProp {
+                name: "altroot".to_string(),
+                value: altroot.to_string_lossy().into_owned(),
+            });
+
+            if self.cachefile.is_none() {
+                props.push(ZProp {
+                    name: "cachefile".to_string(),
+                    value: "none".to_string(),
+                });
+            }
+        }
+
+        props
+    }
+}
+
+fn lookup_host(config: &nvpair::NvListRef, name: String) -> String {
+    config
+        .lookup_string(name)
+        .ok()
+        .and_then(|x| x.into_string().ok())
+        .unwrap_or_default()
+}
+
+/// Decides whether the pool described by `config`, such as
+/// `ImportablePool::config`, is safe to import on this host.
+///
+/// This makes the same multihost (MMP) and hostid checks as `zpool import`
+/// does before it refuses to import a pool without `-f`.
+pub fn import_safety(config: &nvpair::NvListRef) -> Result<ImportSafety> {
+    let state = config.lookup_uint64(sys::zpool_config_pool_state())?;
+
+    if state == u64::from(sys::pool_state::POOL_STATE_DESTROYED) {
+        return Ok(ImportSafety::Destroyed);
+    }
+
+    if state == u64::from(sys::pool_state::POOL_STATE_EXPORTED) {
+        return Ok(ImportSafety::PreviouslyExported);
+    }
+
+    if let Ok(info) = config.lookup_nv_list_ref(sys::zpool_config_load_info()) {
+        match info.lookup_uint64(sys::zpool_config_mmp_state()) {
+            Ok(x) if x == u64::from(sys::mmp_state_MMP_STATE_ACTIVE) => {
+                return Ok(ImportSafety::ActiveOnOtherHost {
+                    hostname: lookup_host(info, sys::zpool_config_mmp_hostname()),
+                    hostid: info
+                        .lookup_uint64(sys::zpool_config_mmp_hostid())
+                        .unwrap_or(0),
+                });
+            }
+            Ok(x) if x == u64::from(sys::mmp_state_MMP_STATE_NO_HOSTID) => {
+                return Ok(ImportSafety::NoLocalHostid);
+            }
+            _ => {}
+        }
+    }
+
+    let hostid = config
+        .lookup_uint64(sys::zpool_config_hostid())
+        .unwrap_or(0);
+    let local_hostid = unsafe { sys::get_system_hostid() };
+
+    if hostid != 0 && hostid != local_hostid {
+        Ok(ImportSafety::ActiveOnOtherHost {
+            hostname: lookup_host(config, sys::zpool_config_hostname()),
+            hostid,
+        })
+    } else {
+        Ok(ImportSafety::SafeToImport)
+    }
+}
+
+pub(crate) fn to_zpool_status(status: sys::zpool_status_t) -> ZpoolStatus {
+    match status {
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_CACHE => ZpoolStatus::CorruptCache,
+        sys::zpool_status_t_ZPOOL_STATUS_MISSING_DEV_R => ZpoolStatus::MissingDevR,
+        sys::zpool_status_t_ZPOOL_STATUS_MISSING_DEV_NR => ZpoolStatus::MissingDevNr,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_R => ZpoolStatus::CorruptLabelR,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_LABEL_NR => ZpoolStatus::CorruptLabelNr,
+        sys::zpool_status_t_ZPOOL_STATUS_BAD_GUID_SUM => ZpoolStatus::BadGuidSum,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_POOL => ZpoolStatus::CorruptPool,
+        sys::zpool_status_t_ZPOOL_STATUS_CORRUPT_DATA => ZpoolStatus::CorruptData,
+        sys::zpool_status_t_ZPOOL_STATUS_FAILING_DEV => ZpoolStatus::FailingDev,
+        sys::zpool_status_t_ZPOOL_STATUS_VERSION_NEWER => ZpoolStatus::VersionNewer,
+        sys::zpool_status_t_ZPOOL_STATUS_HOSTID_MISMATCH => ZpoolStatus::HostidMismatch,
+        sys::zpool_status_t_ZPOOL_STATUS_HOSTID_ACTIVE => ZpoolStatus::HostidActive,
+        sys::zpool_status_t_ZPOOL_STATUS_HOSTID_REQUIRED => ZpoolStatus::HostidRequired,
+        sys::zpool_status_t_ZPOOL_STATUS_IO_FAILURE_WAIT => ZpoolStatus::IoFailureWait,
+        sys::zpool_status_t_ZPOOL_STATUS_IO_FAILURE_CONTINUE => ZpoolStatus::IoFailureContinue,
+        sys::zpool_status_t_ZPOOL_STATUS_IO_FAILURE_MMP => ZpoolStatus::IoFailureMmp,
+        sys::zpool_status_t_ZPOOL_STATUS_BAD_LOG => ZpoolStatus::BadLog,
+        sys::zpool_status_t_ZPOOL_STATUS_ERRATA => ZpoolStatus::Errata,
+        sys::zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_READ => ZpoolStatus::UnsupFeatRead,
+        sys::zpool_status_t_ZPOOL_STATUS_UNSUP_FEAT_WRITE => ZpoolStatus::UnsupFeatWrite,
+        sys::zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_R => ZpoolStatus::FaultedDevR,
+        sys::zpool_status_t_ZPOOL_STATUS_FAULTED_DEV_NR => ZpoolStatus::FaultedDevNr,
+        sys::zpool_status_t_ZPOOL_STATUS_VERSION_OLDER => ZpoolStatus::VersionOlder,
+        sys::zpool_status_t_ZPOOL_STATUS_FEAT_DISABLED => ZpoolStatus::FeatDisabled,
+        sys::zpool_status_t_ZPOOL_STATUS_RESILVERING => ZpoolStatus::Resilvering,
+        sys::zpool_status_t_ZPOOL_STATUS_OFFLINE_DEV => ZpoolStatus::OfflineDev,
+        sys::zpool_status_t_ZPOOL_STATUS_REMOVED_DEV => ZpoolStatus::RemovedDev,
+        sys::zpool_status_t_ZPOOL_STATUS_OK => ZpoolStatus::Ok,
+        _ => ZpoolStatus::Unknown,
+    }
+}
+
+/// A pool found by `Libzfs::find_importable_pools` or `read_cachefile`.
+///
+/// Can be passed back to `Libzfs::import_all` to import it.
+#[derive(Serialize)]
+pub struct ImportablePool {
+    pub name: String,
+    pub guid: u64,
+    pub state: String,
+    pub hostname: Option<String>,
+    pub hostid: Option<u64>,
+    pub status: ZpoolStatus,
+    /// The `ZFS-8000-*` message id describing the action to take, if any.
+    pub msgid: Option<String>,
+    pub vdev: VDev,
+    #[serde(skip)]
+    config: nvpair::NvList,
+}
+
+impl ImportablePool {
+    /// Builds an `ImportablePool` from one of the pool configs
+    /// returned by `zpool_search_import` or stored in a cachefile.
+    pub(crate) fn from_config(config: &nvpair::NvListRef) -> Result<ImportablePool> {
+        let config = config.try_to_owned()?;
+
+        let name = config
+            .lookup_string(sys::zpool_config_pool_name())?
+            .into_string()?;
+
+        let guid = config.lookup_uint64(sys::zpool_config_pool_guid())?;
+
+        let state = config.lookup_uint64(sys::zpool_config_pool_state())?;
+
+        let state =
+            unsafe { CStr::from_ptr(sys::zpool_pool_state_to_name(state as sys::pool_state_t)) }
+                .to_owned()
+                .into_string()?;
+
+        let hostname = config
+            .lookup_string(sys::zpool_config_hostname())
+            .ok()
+            .map(|x| x.into_string())
+            .map_or(Ok(None), |x| x.map(Some))?;
+
+        let hostid = config.lookup_uint64(sys::zpool_config_hostid()).ok();
+
+        let tree = config
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?
+            .try_to_owned()?;
+
+        // Cachefile configs carry no vdev stats, which
+        // zpool_import_status needs to work out a status.
+        let (status, msgid) = if tree.exists(sys::zpool_config_vdev_stats()) {
+            let mut msgid = ptr::null_mut();
+            let mut errata = sys::zpool_errata_ZPOOL_ERRATA_NONE;
+
+            let status = unsafe {
+                sys::zpool_import_status(config.as_ptr() as *mut _, &mut msgid, &mut errata)
+            };
+
+            let msgid = if msgid.is_null() {
+                None
+            } else {
+                Some(unsafe { CStr::from_ptr(msgid) }.to_owned().into_string()?)
+            };
+
+            (to_zpool_status(status), msgid)
+        } else {
+            (ZpoolStatus::Unknown, None)
+        };
+
+        let vdev = enumerate_vdev_tree(&tree)?;
+
+        Ok(ImportablePool {
+            name,
+            guid,
+            state,
+            hostname,
+            hostid,
+            status,
+            msgid,
+            vdev,
+            config,
+        })
+    }
+    /// The raw pool config libzfs found for this pool.
+    pub fn config(&self) -> &nvpair::NvListRef {
+        &self.config
+    }
+    /// Whether this pool can be imported without risking
+    /// it being imported on two hosts at once.
+    pub fn safety(&self) -> Result<ImportSafety> {
+        import_safety(&self.config)
+    }
+}
+
+impl fmt::Debug for ImportablePool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImportablePool")
+            .field("name", &self.name)
+            .field("guid", &self.guid)
+            .field("state", &self.state)
+            .field("hostname", &self.hostname)
+            .field("hostid", &self.hostid)
+            .field("status", &self.status)
+            .field("msgid", &self.msgid)
+            .field("vdev", &self.vdev)
+            .finish()
+    }
+}
+
+/// Reads the pool configs stored in a cachefile, such as `ZPOOL_CACHE`.
+///
+/// The file is decoded in Rust with `libzfs_types::nvlist`, which only
+/// reads the XDR encoding ZFS always writes cachefiles in. It works
+/// without the zfs module loaded and does not need a `Libzfs` handle,
+/// but each `ImportablePool` still keeps its config in libnvpair and
+/// names its state through libzfs, so both libraries must be installed.
+///
+/// Cachefile configs have no device stats, so `status` is always
+/// `ZpoolStatus::Unknown` and the devices in `vdev` are `"UNKNOWN"`.
+pub fn read_cachefile<P: AsRef<Path>>(path: P) -> Result<Vec<ImportablePool>> {
+    let buf = fs::read(path)?;
+    let cache = plain::NvList::from_xdr(&buf)?;
+
+    cache
+        .pairs
+        .iter()
+        .map(|pair| match pair.value {
+            plain::NvValue::NvList(ref config) => {
+                let config = nvpair::NvList::unpack(&config.to_xdr()?)?;
+
+                ImportablePool::from_config(&config)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("cachefile entry {:?} is not a pool config", pair.name),
+            )
+            .into()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libzfs::Libzfs;
+    use nvpair::{NvEncode, NvEncoding};
+    use test_pool::TestPool;
+    use zpool::ExportOptions;
+
+    #[test]
+    fn destroyed_pool_is_not_safe() {
+        let mut config = nvpair::NvList::new_unique_names().unwrap();
+
+        u64::from(sys::pool_state::POOL_STATE_DESTROYED)
+            .insert(sys::zpool_config_pool_state(), &mut config)
+            .unwrap();
+
+        let safety = import_safety(&config).expect("could not check pool");
+
+        assert_eq!(safety, ImportSafety::Destroyed);
+        assert!(!safety.is_safe());
+    }
+
+    fn vdev_config(vdev_type: &[u8]) -> nvpair::NvList {
+        let mut nvl = nvpair::NvList::new_unique_names().unwrap();
+
+        CStr::from_bytes_with_nul(vdev_type)
+            .unwrap()
+            .insert(sys::zpool_config_type(), &mut nvl)
+            .unwrap();
+
+        nvl
+    }
+
+    /// A cachefile as `spa_config_write` packs it: configs
+    /// keyed by pool name, without `vdev_stats`.
+    #[test]
+    fn read_cachefile_without_stats() {
+        let leaves: Vec<_> = ["/dev/sda", "/dev/sdb"]
+            .iter()
+            .map(|path| {
+                let mut leaf = vdev_config(sys::VDEV_TYPE_DISK);
+
+                path.insert(sys::zpool_config_path(), &mut leaf).unwrap();
+                0u64.insert(sys::zpool_config_whole_disk(), &mut leaf)
+                    .unwrap();
+
+                leaf
+            })
+            .collect();
+
+        let mut mirror = vdev_config(sys::VDEV_TYPE_MIRROR);
+        leaves
+            .insert(sys::zpool_config_children(), &mut mirror)
+            .unwrap();
+
+        let mut root = vdev_config(sys::VDEV_TYPE_ROOT);
+        vec![mirror]
+            .insert(sys::zpool_config_children(), &mut root)
+            .unwrap();
+
+        let mut config = nvpair::NvList::new_unique_names().unwrap();
+        "cached"
+            .insert(sys::zpool_config_pool_name(), &mut config)
+            .unwrap();
+        42u64
+            .insert(sys::zpool_config_pool_guid(), &mut config)
+            .unwrap();
+        u64::from(sys::pool_state::POOL_STATE_ACTIVE)
+            .insert(sys::zpool_config_pool_state(), &mut config)
+            .unwrap();
+        root.insert(sys::zpool_config_vdev_tree(), &mut config)
+            .unwrap();
+
+        let mut cache = nvpair::NvList::new_unique_names().unwrap();
+        config.insert("cached", &mut cache).unwrap();
+
+        let path = "/tmp/libzfs-test-read-cachefile.cache";
+        fs::write(path, cache.pack(NvEncoding::Xdr).unwrap()).unwrap();
+
+        let pools = read_cachefile(path).expect("could not read cachefile");
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].name, "cached");
+        assert_eq!(pools[0].guid, 42);
+        assert_eq!(pools[0].status, ZpoolStatus::Unknown);
+
+        match pools[0].vdev {
+            VDev::Root { ref children, .. } => match children[0] {
+                VDev::Mirror { ref children, .. } => {
+                    assert_eq!(children.len(), 2);
+
+                    for x in children {
+                        match *x {
+                            VDev::Disk {
+                                ref state,
+                                ref stats,
+                                ..
+                            } => {
+                                assert_eq!(state, "UNKNOWN");
+                                assert!(stats.is_none());
+                            }
+                            _ => panic!("did not find disk"),
+                        }
+                    }
+                }
+                _ => panic!("did not find mirror"),
+            },
+            _ => panic!("did not find root device"),
+        };
+    }
+
+    #[test]
+    fn import_safety_after_export() {
+        let fixture = TestPool::new("import_safety", 1);
+        let mut z = Libzfs::new();
+
+        let pool = fixture.create(&mut z, &fixture.stripe());
+
+        pool.export().expect("could not export pool");
+
+        let options = ImportOptions::new()
+            .search_path("/tmp/libzfs-test/import_safety")
+            .name("import_safety");
+
+        let pools = z
+            .find_importable_pools_with(&options)
+            .expect("could not find pools");
+
+        assert_eq!(pools[0].state, "EXPORTED");
+        assert_eq!(
+            pools[0].safety().expect("could not check pool"),
+            ImportSafety::PreviouslyExported
+        );
+
+        z.import_all_with(&pools, &options)
+            .expect("could not import pool");
+
+        let pool = z
+            .pool_by_name("import_safety")
+            .expect("could not find pool");
+
+        let hardforce = ExportOptions {
+            hardforce: true,
+            ..ExportOptions::default()
+        };
+
+        pool.export_with(&hardforce).expect("could not export pool");
+
+        let pools = z
+            .find_importable_pools_with(&options)
+            .expect("could not find pools");
+
+        assert_eq!(
+            pools[0].safety().expect("could not check pool"),
+            ImportSafety::SafeToImport
+        );
+
+        z.import_all_with(&pools, &options)
+            .expect("could not import pool");
+
+        z.pool_by_name("import_safety")
+            .expect("could not find pool")
+            .destroy(true, "libzfs test")
+            .expect("could not destroy pool");
+    }
+
+    #[test]
+    fn read_pool_from_cachefile() {
+        let fixture = TestPool::new("read_cachefile", 2);
+        let mut z = Libzfs::new();
+
+        let pool = fixture.create(&mut z, &fixture.mirror());
+        let guid = pool.guid();
+
+        let cached = read_cachefile(ZPOOL_CACHE)
+            .expect("could not read cachefile")
+            .into_iter()
+            .find(|p| p.name == "read_cachefile")
+            .expect("pool not found in cachefile");
+
+        assert_eq!(cached.guid, guid);
+
+        match cached.vdev {
+            VDev::Root { ref children, .. } => assert_eq!(children.len(), 1),
+            _ => panic!("did not find root device"),
+        };
+    }
+}
@@ -0,0 +1,378 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Read and clear the vdev labels on a device or image file.
+//!
+//! Labels are read straight from the device and decoded in Rust,
+//! so this works on disks whose pools can't be imported.
+//!
+
+extern crate libzfs_sys as sys;
+
+use libzfs_types::nvlist::{NvList, NvValue};
+use libzfs_types::{LibZfsError, Result, Uberblock, VDevLabel, ZfsErrorKind};
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+const VDEV_LABELS: u64 = 4;
+const VDEV_LABEL_SIZE: u64 = 256 * 1024;
+/// The packed label nvlist starts after two 8K pads
+const VDEV_PHYS_OFFSET: usize = 16 * 1024;
+/// 112K less the trailing `zio_eck_t`
+const VDEV_PHYS_NVLIST_SIZE: usize = 112 * 1024 - 40;
+const VDEV_UBERBLOCK_OFFSET: usize = 128 * 1024;
+const VDEV_UBERBLOCK_RING: usize = 128 * 1024;
+const UBERBLOCK_SHIFT: u64 = 10;
+const MAX_UBERBLOCK_SHIFT: u64 = 13;
+const UBERBLOCK_MAGIC: u64 = 0x00ba_b10c;
+
+fn get_u64(nvl: &NvList, name: &str) -> Option<u64> {
+    match nvl.get(name) {
+        Some(&NvValue::UInt64(x)) => Some(x),
+        _ => None,
+    }
+}
+
+fn get_string(nvl: &NvList, name: &str) -> Option<String> {
+    match nvl.get(name) {
+        Some(NvValue::String(x)) => Some(x.clone()),
+        _ => None,
+    }
+}
+
+/// The usable size of the device, rounded down to whole labels
+fn label_area_size(f: &mut File) -> Result<u64> {
+    let size = f.seek(SeekFrom::End(0))?;
+    let size = size - size % VDEV_LABEL_SIZE;
+
+    if size < VDEV_LABELS * VDEV_LABEL_SIZE {
+        Err(LibZfsError::Io(Error::new(
+            ErrorKind::InvalidInput,
+            "device is too small to hold vdev labels",
+        )))
+    } else {
+        Ok(size)
+    }
+}
+
+fn label_offset(size: u64, index: u64) -> u64 {
+    let end = if index < VDEV_LABELS / 2 {
+        0
+    } else {
+        size - VDEV_LABELS * VDEV_LABEL_SIZE
+    };
+
+    index * VDEV_LABEL_SIZE + end
+}
+
+fn read_u64(buf: &[u8], little_endian: bool) -> u64 {
+    let bytes = buf[..8].iter();
+
+    if little_endian {
+        bytes.rev().fold(0, |acc, x| (acc << 8) | u64::from(*x))
+    } else {
+        bytes.fold(0, |acc, x| (acc << 8) | u64::from(*x))
+    }
+}
+
+/// Decode the uberblock ring, skipping slots without a valid magic.
+///
+/// Uberblocks are written in the byte order of the host that wrote them.
+fn parse_uberblocks(ring: &[u8], ashift: u64) -> Vec<Uberblock> {
+    let shift = ashift.clamp(UBERBLOCK_SHIFT, MAX_UBERBLOCK_SHIFT);
+
+    ring.chunks(1 << shift)
+        .enumerate()
+        .filter_map(|(slot, ub)| {
+            let little_endian = if read_u64(ub, true) == UBERBLOCK_MAGIC {
+                true
+            } else if read_u64(ub, false) == UBERBLOCK_MAGIC {
+                false
+            } else {
+                return None;
+            };
+
+            Some(Uberblock {
+                slot,
+                version: read_u64(&ub[8..], little_endian),
+                txg: read_u64(&ub[16..], little_endian),
+                guid_sum: read_u64(&ub[24..], little_endian),
+                timestamp: read_u64(&ub[32..], little_endian),
+            })
+        })
+        .collect()
+}
+
+fn parse_label(index: usize, offset: u64, buf: &[u8]) -> VDevLabel {
+    let phys = &buf[VDEV_PHYS_OFFSET..VDEV_PHYS_OFFSET + VDEV_PHYS_NVLIST_SIZE];
+    let config = NvList::from_xdr(phys).ok();
+
+    let ashift = match config
+        .as_ref()
+        .map(|c| c.get(&sys::zpool_config_vdev_tree()))
+    {
+        Some(Some(NvValue::NvList(tree))) => get_u64(tree, &sys::zpool_config_ashift()),
+        _ => None,
+    };
+
+    let ring = &buf[VDEV_UBERBLOCK_OFFSET..VDEV_UBERBLOCK_OFFSET + VDEV_UBERBLOCK_RING];
+    let uberblocks = parse_uberblocks(ring, ashift.unwrap_or(0));
+
+    let field = |name: String| config.as_ref().and_then(|c| get_u64(c, &name));
+    let string = |name: String| config.as_ref().and_then(|c| get_string(c, &name));
+
+    let state = field(sys::zpool_config_pool_state()).map(|x| {
+        unsafe { CStr::from_ptr(sys::zpool_pool_state_to_name(x as sys::pool_state_t)) }
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    VDevLabel {
+        index,
+        offset,
+        pool_name: string(sys::zpool_config_pool_name()),
+        pool_guid: field(sys::zpool_config_pool_guid()),
+        guid: field(sys::zpool_config_guid()),
+        txg: field(sys::zpool_config_pool_txg()),
+        hostid: field(sys::zpool_config_hostid()),
+        hostname: string(sys::zpool_config_hostname()),
+        state,
+        config,
+        uberblocks,
+    }
+}
+
+/// Read all four vdev labels from a block device or image file.
+///
+/// Always returns four labels; use `VDevLabel::is_valid` to see
+/// which of them held a config.
+pub fn read_labels<P: AsRef<Path>>(path: P) -> Result<Vec<VDevLabel>> {
+    let mut f = File::open(path)?;
+    let size = label_area_size(&mut f)?;
+    let mut buf = vec![0u8; VDEV_LABEL_SIZE as usize];
+
+    (0..VDEV_LABELS)
+        .map(|i| {
+            let offset = label_offset(size, i);
+
+            f.seek(SeekFrom::Start(offset))?;
+            f.read_exact(&mut buf)?;
+
+            Ok(parse_label(i as usize, offset, &buf))
+        })
+        .collect()
+}
+
+/// Wipe all four vdev labels, like `zpool labelclear`.
+///
+/// This destroys the device's membership of its pool. As a safeguard
+/// `confirm_guid` must match the pool guid, or the vdev guid, found in
+/// one of the labels. Devices whose labels say their pool is `ACTIVE`
+/// are refused with `ZfsErrorKind::ActivePool` unless `force` is set.
+///
+/// Block devices are opened with `O_EXCL`, so one still held open by
+/// an imported pool or a mounted filesystem fails with `EBUSY`, even
+/// with `force`.
+pub fn clear_labels<P: AsRef<Path>>(path: P, confirm_guid: u64, force: bool) -> Result<()> {
+    let path = path.as_ref();
+    let labels = read_labels(path)?;
+
+    let confirmed = labels
+        .iter()
+        .any(|l| l.pool_guid == Some(confirm_guid) || l.guid == Some(confirm_guid));
+
+    if !confirmed {
+        return Err(LibZfsError::Io(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "no label on {} belongs to guid {}",
+                path.display(),
+                confirm_guid
+            ),
+        )));
+    }
+
+    let active = labels.iter().any(|l| {
+        l.config
+            .as_ref()
+            .and_then(|c| get_u64(c, &sys::zpool_config_pool_state()))
+            == Some(u64::from(sys::pool_state::POOL_STATE_ACTIVE))
+    });
+
+    if active && !force {
+        return Err(LibZfsError::Zfs(
+            ZfsErrorKind::ActivePool,
+            format!(
+                "{} is part of an active pool, force is needed to clear it",
+                path.display()
+            ),
+        ));
+    }
+
+    let mut f = OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_EXCL)
+        .open(path)?;
+    let size = label_area_size(&mut f)?;
+    let zeros = vec![0u8; VDEV_LABEL_SIZE as usize];
+
+    for i in 0..VDEV_LABELS {
+        f.seek(SeekFrom::Start(label_offset(size, i)))?;
+        f.write_all(&zeros)?;
+    }
+
+    f.sync_all()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libzfs::Libzfs;
+    use std::fs;
+    use test_pool::TestPool;
+
+    const POOL_GUID: u64 = 0x1234_5678;
+
+    /// Write a label config to all four labels of a sparse file,
+    /// with a single little endian uberblock in label 0.
+    fn create_label_fixture(path: &str) {
+        let mut tree = NvList::default();
+        tree.insert(sys::zpool_config_ashift(), NvValue::UInt64(12));
+
+        let mut config = NvList::default();
+        config.insert(
+            sys::zpool_config_pool_name(),
+            NvValue::String("tank".into()),
+        );
+        config.insert(sys::zpool_config_pool_guid(), NvValue::UInt64(POOL_GUID));
+        config.insert(sys::zpool_config_guid(), NvValue::UInt64(99));
+        config.insert(sys::zpool_config_pool_txg(), NvValue::UInt64(42));
+        config.insert(
+            sys::zpool_config_pool_state(),
+            NvValue::UInt64(u64::from(sys::pool_state::POOL_STATE_EXPORTED)),
+        );
+        config.insert(
+            sys::zpool_config_hostname(),
+            NvValue::String("node1".into()),
+        );
+        config.insert(sys::zpool_config_hostid(), NvValue::UInt64(0xabcd));
+        config.insert(sys::zpool_config_vdev_tree(), NvValue::NvList(tree));
+
+        let packed = config.to_xdr().unwrap();
+
+        let mut ub = vec![];
+        for x in &[UBERBLOCK_MAGIC, 5000, 42, 7, 1_500_000_000] {
+            for i in 0..8 {
+                ub.push((x >> (i * 8)) as u8);
+            }
+        }
+
+        let mut f = File::create(path).unwrap();
+        let size = 4 * 1024 * 1024;
+        f.set_len(size).unwrap();
+
+        for i in 0..VDEV_LABELS {
+            let offset = label_offset(size, i);
+
+            f.seek(SeekFrom::Start(offset + VDEV_PHYS_OFFSET as u64))
+                .unwrap();
+            f.write_all(&packed).unwrap();
+        }
+
+        // slot 3 of label 0, with 4K slots for ashift 12
+        f.seek(SeekFrom::Start((VDEV_UBERBLOCK_OFFSET + 3 * 4096) as u64))
+            .unwrap();
+        f.write_all(&ub).unwrap();
+    }
+
+    #[test]
+    fn read_label_fixture() {
+        let path = "/tmp/read_label_fixture.img";
+        create_label_fixture(path);
+
+        let labels = read_labels(path).expect("could not read labels");
+
+        assert_eq!(labels.len(), 4);
+        assert_eq!(labels[3].offset, 4 * 1024 * 1024 - VDEV_LABEL_SIZE);
+
+        for l in &labels {
+            assert!(l.is_valid());
+            assert_eq!(l.pool_name, Some("tank".to_string()));
+            assert_eq!(l.pool_guid, Some(POOL_GUID));
+            assert_eq!(l.guid, Some(99));
+            assert_eq!(l.txg, Some(42));
+            assert_eq!(l.hostid, Some(0xabcd));
+            assert_eq!(l.hostname, Some("node1".to_string()));
+            assert_eq!(l.state, Some("EXPORTED".to_string()));
+        }
+
+        let ub = labels[0].active_uberblock().expect("no uberblock");
+        assert_eq!(ub.slot, 3);
+        assert_eq!(ub.txg, 42);
+        assert_eq!(ub.timestamp, 1_500_000_000);
+        assert!(labels[1].uberblocks.is_empty());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn clear_label_fixture() {
+        let path = "/tmp/clear_label_fixture.img";
+        create_label_fixture(path);
+
+        clear_labels(path, 1, false).expect_err("cleared with the wrong guid");
+
+        clear_labels(path, POOL_GUID, false).expect("could not clear labels");
+
+        let labels = read_labels(path).expect("could not read labels");
+        assert!(labels
+            .iter()
+            .all(|l| !l.is_valid() && l.uberblocks.is_empty()));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_and_clear_pool_labels() {
+        let fixture = TestPool::new("labels", 1);
+        let path = &fixture.paths[0];
+        let mut z = Libzfs::new();
+
+        let pool = fixture.create(&mut z, &fixture.stripe());
+        let guid = pool.guid();
+
+        let labels = read_labels(path).expect("could not read labels");
+
+        for l in &labels {
+            assert!(l.is_valid());
+            assert_eq!(l.pool_name, Some("labels".to_string()));
+            assert_eq!(l.pool_guid, Some(guid));
+            assert_eq!(l.state, Some("ACTIVE".to_string()));
+        }
+
+        assert!(labels.iter().any(|l| l.active_uberblock().is_some()));
+
+        match clear_labels(path, guid, false) {
+            Err(LibZfsError::Zfs(ZfsErrorKind::ActivePool, _)) => {}
+            x => panic!("cleared an active pool: {:?}", x),
+        }
+
+        pool.export().expect("could not export pool");
+
+        let labels = read_labels(path).expect("could not read labels");
+        assert!(labels
+            .iter()
+            .all(|l| l.state == Some("EXPORTED".to_string())));
+
+        clear_labels(path, guid, false).expect("could not clear labels");
+
+        let labels = read_labels(path).expect("could not read labels");
+        assert!(labels.iter().all(|l| !l.is_valid()));
+    }
+}
@@ -13,11 +13,16 @@ extern crate foreign_types;
 #[macro_use]
 extern crate lazy_static;
 
+extern crate libc;
+
 extern crate libzfs_sys as sys;
 
 pub extern crate libzfs_types;
 
-mod nvpair;
+pub mod nvpair;
+
+mod nvpair_serde;
+pub use nvpair_serde::{from_nv_list, to_nv_list};
 
 pub use libzfs_types::*;
 
@@ -28,13 +33,22 @@ pub mod zprop_list;
 pub use zprop_list::ZProp;
 
 pub mod zfs;
-pub use zfs::Zfs;
+pub use zfs::{DatasetIter, DatasetTypes, ListOptions, RollbackOptions, Zfs};
 
 pub mod zpool;
-pub use zpool::Zpool;
+pub use zpool::{ExportOptions, OnlineFlags, ScanCommand, Zpool};
+
+pub mod import;
+pub use import::{import_safety, read_cachefile, ImportOptions, ImportablePool, ZPOOL_CACHE};
+
+pub mod label;
+pub use label::{clear_labels, read_labels};
 
 pub mod libzfs;
 pub use libzfs::Libzfs;
 
 pub mod state;
 pub use state::*;
+
+#[cfg(test)]
+mod test_pool;
@@ -4,21 +4,122 @@
 
 extern crate libzfs_sys as sys;
 
-use libzfs_types::{LibZfsError, Result};
+use import::{ImportFilter, ImportOptions, ImportablePool};
+use libzfs_types::{LibZfsError, Result, SnapshotError, SnapshotResult, ZfsErrorKind};
 use nvpair;
 use nvpair::ForeignType;
-use std::ffi::CString;
-use std::io::Error;
-use std::os::raw::{c_int, c_void};
+use std::ffi::{CStr, CString};
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
 use std::ptr;
 use std::sync::Mutex;
-use zfs::Zfs;
-use zpool::Zpool;
+use vdev::{vdev_to_nv_list, VDev};
+use zfs::{self, Zfs};
+use zpool::{ExportOptions, Zpool};
+use zprop_list::{to_nv_list, ZProp};
 
 lazy_static! {
     pub static ref LOCK: Mutex<()> = Mutex::new(());
 }
 
+fn to_error_kind(code: sys::zfs_error_t) -> ZfsErrorKind {
+    match code {
+        sys::zfs_error_EZFS_NOMEM => ZfsErrorKind::NoMem,
+        sys::zfs_error_EZFS_BADPROP => ZfsErrorKind::BadProp,
+        sys::zfs_error_EZFS_PROPREADONLY => ZfsErrorKind::PropReadonly,
+        sys::zfs_error_EZFS_PROPTYPE => ZfsErrorKind::PropType,
+        sys::zfs_error_EZFS_PROPNONINHERIT => ZfsErrorKind::PropNonInherit,
+        sys::zfs_error_EZFS_PROPSPACE => ZfsErrorKind::PropSpace,
+        sys::zfs_error_EZFS_BADTYPE => ZfsErrorKind::BadType,
+        sys::zfs_error_EZFS_BUSY => ZfsErrorKind::Busy,
+        sys::zfs_error_EZFS_EXISTS => ZfsErrorKind::Exists,
+        sys::zfs_error_EZFS_NOENT => ZfsErrorKind::NoEnt,
+        sys::zfs_error_EZFS_BADSTREAM => ZfsErrorKind::BadStream,
+        sys::zfs_error_EZFS_DSREADONLY => ZfsErrorKind::DsReadonly,
+        sys::zfs_error_EZFS_VOLTOOBIG => ZfsErrorKind::VolTooBig,
+        sys::zfs_error_EZFS_INVALIDNAME => ZfsErrorKind::InvalidName,
+        sys::zfs_error_EZFS_BADRESTORE => ZfsErrorKind::BadRestore,
+        sys::zfs_error_EZFS_BADBACKUP => ZfsErrorKind::BadBackup,
+        sys::zfs_error_EZFS_BADTARGET => ZfsErrorKind::BadTarget,
+        sys::zfs_error_EZFS_NODEVICE => ZfsErrorKind::NoDevice,
+        sys::zfs_error_EZFS_BADDEV => ZfsErrorKind::BadDev,
+        sys::zfs_error_EZFS_NOREPLICAS => ZfsErrorKind::NoReplicas,
+        sys::zfs_error_EZFS_RESILVERING => ZfsErrorKind::Resilvering,
+        sys::zfs_error_EZFS_BADVERSION => ZfsErrorKind::BadVersion,
+        sys::zfs_error_EZFS_POOLUNAVAIL => ZfsErrorKind::PoolUnavail,
+        sys::zfs_error_EZFS_DEVOVERFLOW => ZfsErrorKind::DevOverflow,
+        sys::zfs_error_EZFS_BADPATH => ZfsErrorKind::BadPath,
+        sys::zfs_error_EZFS_CROSSTARGET => ZfsErrorKind::CrossTarget,
+        sys::zfs_error_EZFS_ZONED => ZfsErrorKind::Zoned,
+        sys::zfs_error_EZFS_MOUNTFAILED => ZfsErrorKind::MountFailed,
+        sys::zfs_error_EZFS_UMOUNTFAILED => ZfsErrorKind::UmountFailed,
+        sys::zfs_error_EZFS_UNSHARENFSFAILED => ZfsErrorKind::UnshareNfsFailed,
+        sys::zfs_error_EZFS_SHARENFSFAILED => ZfsErrorKind::ShareNfsFailed,
+        sys::zfs_error_EZFS_PERM => ZfsErrorKind::Perm,
+        sys::zfs_error_EZFS_NOSPC => ZfsErrorKind::NoSpc,
+        sys::zfs_error_EZFS_FAULT => ZfsErrorKind::Fault,
+        sys::zfs_error_EZFS_IO => ZfsErrorKind::Io,
+        sys::zfs_error_EZFS_INTR => ZfsErrorKind::Intr,
+        sys::zfs_error_EZFS_ISSPARE => ZfsErrorKind::IsSpare,
+        sys::zfs_error_EZFS_INVALCONFIG => ZfsErrorKind::InvalConfig,
+        sys::zfs_error_EZFS_RECURSIVE => ZfsErrorKind::Recursive,
+        sys::zfs_error_EZFS_NOHISTORY => ZfsErrorKind::NoHistory,
+        sys::zfs_error_EZFS_POOLPROPS => ZfsErrorKind::PoolProps,
+        sys::zfs_error_EZFS_POOL_NOTSUP => ZfsErrorKind::PoolNotSup,
+        sys::zfs_error_EZFS_POOL_INVALARG => ZfsErrorKind::PoolInvalArg,
+        sys::zfs_error_EZFS_NAMETOOLONG => ZfsErrorKind::NameTooLong,
+        sys::zfs_error_EZFS_OPENFAILED => ZfsErrorKind::OpenFailed,
+        sys::zfs_error_EZFS_NOCAP => ZfsErrorKind::NoCap,
+        sys::zfs_error_EZFS_LABELFAILED => ZfsErrorKind::LabelFailed,
+        sys::zfs_error_EZFS_BADWHO => ZfsErrorKind::BadWho,
+        sys::zfs_error_EZFS_BADPERM => ZfsErrorKind::BadPerm,
+        sys::zfs_error_EZFS_BADPERMSET => ZfsErrorKind::BadPermSet,
+        sys::zfs_error_EZFS_NODELEGATION => ZfsErrorKind::NoDelegation,
+        sys::zfs_error_EZFS_UNSHARESMBFAILED => ZfsErrorKind::UnshareSmbFailed,
+        sys::zfs_error_EZFS_SHARESMBFAILED => ZfsErrorKind::ShareSmbFailed,
+        sys::zfs_error_EZFS_BADCACHE => ZfsErrorKind::BadCache,
+        sys::zfs_error_EZFS_ISL2CACHE => ZfsErrorKind::IsL2Cache,
+        sys::zfs_error_EZFS_VDEVNOTSUP => ZfsErrorKind::VdevNotSup,
+        sys::zfs_error_EZFS_NOTSUP => ZfsErrorKind::NotSup,
+        sys::zfs_error_EZFS_ACTIVE_SPARE => ZfsErrorKind::ActiveSpare,
+        sys::zfs_error_EZFS_UNPLAYED_LOGS => ZfsErrorKind::UnplayedLogs,
+        sys::zfs_error_EZFS_REFTAG_RELE => ZfsErrorKind::ReftagRele,
+        sys::zfs_error_EZFS_REFTAG_HOLD => ZfsErrorKind::ReftagHold,
+        sys::zfs_error_EZFS_TAGTOOLONG => ZfsErrorKind::TagTooLong,
+        sys::zfs_error_EZFS_PIPEFAILED => ZfsErrorKind::PipeFailed,
+        sys::zfs_error_EZFS_THREADCREATEFAILED => ZfsErrorKind::ThreadCreateFailed,
+        sys::zfs_error_EZFS_POSTSPLIT_ONLINE => ZfsErrorKind::PostSplitOnline,
+        sys::zfs_error_EZFS_SCRUBBING => ZfsErrorKind::Scrubbing,
+        sys::zfs_error_EZFS_NO_SCRUB => ZfsErrorKind::NoScrub,
+        sys::zfs_error_EZFS_DIFF => ZfsErrorKind::Diff,
+        sys::zfs_error_EZFS_DIFFDATA => ZfsErrorKind::DiffData,
+        sys::zfs_error_EZFS_POOLREADONLY => ZfsErrorKind::PoolReadonly,
+        sys::zfs_error_EZFS_SCRUB_PAUSED => ZfsErrorKind::ScrubPaused,
+        sys::zfs_error_EZFS_ACTIVE_POOL => ZfsErrorKind::ActivePool,
+        _ => ZfsErrorKind::Unknown,
+    }
+}
+
+/// Builds a `LibZfsError` from the last error
+/// libzfs recorded on the given handle.
+pub(crate) fn last_error(raw: *mut sys::libzfs_handle_t) -> LibZfsError {
+    let (code, action, description) = unsafe {
+        (
+            sys::libzfs_errno(raw),
+            CStr::from_ptr(sys::libzfs_error_action(raw)),
+            CStr::from_ptr(sys::libzfs_error_description(raw)),
+        )
+    };
+
+    let description = match action.to_string_lossy() {
+        ref x if x.is_empty() => description.to_string_lossy().into_owned(),
+        x => format!("{}: {}", x, description.to_string_lossy()),
+    };
+
+    LibZfsError::Zfs(to_error_kind(code as sys::zfs_error_t), description)
+}
+
 pub struct Libzfs {
     raw: *mut sys::libzfs_handle_t,
 }
@@ -63,43 +164,288 @@ impl Libzfs {
             }
         }
     }
-    pub fn find_importable_pools(&mut self) -> nvpair::NvList {
+    /// Creates a new pool named `name` from a `VDev::Root` layout.
+    ///
+    /// `pool_props` are set on the pool and `fs_props` on its root dataset.
+    /// Returns a handle to the newly created pool.
+    pub fn create_pool(
+        &mut self,
+        name: &str,
+        vdev: &VDev,
+        pool_props: &[ZProp],
+        fs_props: &[ZProp],
+    ) -> Result<Zpool> {
+        match *vdev {
+            VDev::Root { .. } => {}
+            _ => {
+                return Err(LibZfsError::Io(Error::new(
+                    ErrorKind::InvalidInput,
+                    "pool layout must be a root vdev",
+                )))
+            }
+        }
+
+        let nvroot = vdev_to_nv_list(vdev)?;
+
+        let pool_props = if pool_props.is_empty() {
+            None
+        } else {
+            Some(to_nv_list(pool_props)?)
+        };
+
+        let fs_props = if fs_props.is_empty() {
+            None
+        } else {
+            Some(to_nv_list(fs_props)?)
+        };
+
+        let pool_name = CString::new(name).unwrap();
+
         let _l = LOCK.lock().unwrap();
-        unsafe {
-            sys::thread_init();
-            let mut args = sys::import_args();
 
-            let x = sys::zpool_search_import(self.raw, &mut args as *mut sys::importargs);
-            sys::thread_fini();
+        let code = unsafe {
+            sys::zpool_create(
+                self.raw,
+                pool_name.as_ptr(),
+                nvroot.as_ptr(),
+                pool_props.as_ref().map_or(ptr::null_mut(), |x| x.as_ptr()),
+                fs_props.as_ref().map_or(ptr::null_mut(), |x| x.as_ptr()),
+            )
+        };
 
-            nvpair::NvList::from_ptr(x)
+        match code {
+            0 => self
+                .pool_by_name(name)
+                .ok_or_else(|| LibZfsError::PoolNotFound(Some(name.to_string()), None)),
+            _ => Err(last_error(self.raw)),
         }
     }
-    pub fn import_all(&mut self, nvl: &nvpair::NvList) -> Result<Vec<()>> {
-        nvl.iter()
+    /// Atomically creates several snapshots, given by their full
+    /// `<dataset>@<name>` names. They must all be in the same pool.
+    ///
+    /// Either every snapshot is created or none are. On failure
+    /// `SnapshotError::Failed` names each snapshot that caused it.
+    /// Only user properties may be given in `props`.
+    ///
+    /// This calls libzfs_core's `lzc_snapshot` rather than libzfs's
+    /// `zfs_snapshot_nvl`: only `lzc_snapshot` hands back the errlist
+    /// mapping each failing snapshot to its errno, while
+    /// `zfs_snapshot_nvl` consumes it and reports a single error.
+    pub fn snapshot_many(&mut self, names: &[&str], props: &[ZProp]) -> SnapshotResult<Vec<Zfs>> {
+        if let Some(name) = names.iter().find(|x| !x.contains('@')) {
+            return Err(SnapshotError::Other(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a snapshot name", name),
+            ))));
+        }
+
+        let mut snaps = nvpair::NvList::new_unique_names()
+            .map_err(|e| SnapshotError::Other(LibZfsError::Io(e)))?;
+
+        for name in names {
+            snaps
+                .add_boolean(*name)
+                .map_err(|e| SnapshotError::Other(LibZfsError::Io(e)))?;
+        }
+
+        let props = to_nv_list(props).map_err(SnapshotError::Other)?;
+        let mut errlist = ptr::null_mut();
+
+        let code = unsafe { sys::lzc_snapshot(snaps.as_ptr(), props.as_ptr(), &mut errlist) };
+
+        if code != 0 {
+            let errs = if errlist.is_null() {
+                vec![]
+            } else {
+                let errlist = unsafe { nvpair::NvList::from_ptr(errlist) };
+
+                errlist
+                    .iter()
+                    .map(|x| {
+                        let errno = match x.value() {
+                            Ok(nvpair::NvValue::Int32(e)) => e,
+                            _ => code,
+                        };
+
+                        (
+                            x.name().to_string_lossy().into_owned(),
+                            LibZfsError::Io(Error::from_raw_os_error(errno)),
+                        )
+                    })
+                    .collect()
+            };
+
+            return Err(if errs.is_empty() {
+                SnapshotError::Other(LibZfsError::Io(Error::from_raw_os_error(code)))
+            } else {
+                SnapshotError::Failed(errs)
+            });
+        }
+
+        names
+            .iter()
             .map(|x| {
-                let nvl2 = x.value_nv_list()?;
+                zfs::open(self.raw, x, sys::zfs_type_t::ZFS_TYPE_SNAPSHOT)
+                    .map_err(SnapshotError::Other)
+            })
+            .collect()
+    }
+    pub fn find_importable_pools(&mut self) -> Result<Vec<ImportablePool>> {
+        self.find_importable_pools_with(&ImportOptions::default())
+    }
+    /// Searches for importable pools using the device paths,
+    /// cachefile and name or guid filter in `options`.
+    ///
+    /// libzfs only applies the filter when reading a cachefile, so the
+    /// pools found by a device scan are filtered here, like `zpool import`.
+    pub fn find_importable_pools_with(
+        &mut self,
+        options: &ImportOptions,
+    ) -> Result<Vec<ImportablePool>> {
+        let paths: Vec<CString> = options
+            .search_paths
+            .iter()
+            .map(|x| CString::new(x.as_os_str().as_bytes()).unwrap())
+            .collect();
+        let mut path_ptrs: Vec<*mut c_char> = paths.iter().map(|x| x.as_ptr() as *mut _).collect();
+
+        let cachefile = options
+            .cachefile
+            .as_ref()
+            .map(|x| CString::new(x.as_os_str().as_bytes()).unwrap());
+
+        let pool_name = match options.filter {
+            Some(ImportFilter::Name(ref x)) => Some(CString::new(x.as_str()).unwrap()),
+            _ => None,
+        };
+
+        let mut args = sys::import_args();
+
+        if !path_ptrs.is_empty() {
+            args.path = path_ptrs.as_mut_ptr();
+            args.paths = path_ptrs.len() as c_int;
+        }
+
+        if let Some(ref x) = cachefile {
+            args.cachefile = x.as_ptr() as *mut _;
+        }
+
+        if let Some(ref x) = pool_name {
+            args.poolname = x.as_ptr() as *mut _;
+        }
+
+        if let Some(ImportFilter::Guid(guid)) = options.filter {
+            args.guid = guid;
+        }
+
+        let nvl = {
+            let _l = LOCK.lock().unwrap();
+            unsafe {
+                sys::thread_init();
+
+                let x = sys::zpool_search_import(self.raw, &mut args as *mut sys::importargs);
+                sys::thread_fini();
+
+                if x.is_null() {
+                    return Err(last_error(self.raw));
+                }
+
+                nvpair::NvList::from_ptr(x)
+            }
+        };
+
+        let mut pools = vec![];
+
+        for x in nvl.iter() {
+            let config = nvl.lookup_nv_list_ref(x.name())?;
+
+            let wanted = match options.filter {
+                Some(ref filter) => filter.matches(config),
+                None => true,
+            };
+
+            if wanted {
+                pools.push(ImportablePool::from_config(config)?);
+            }
+        }
+
+        Ok(pools)
+    }
+    pub fn import_all(&mut self, pools: &[ImportablePool]) -> Result<Vec<()>> {
+        self.import_all_with(pools, &ImportOptions::default())
+    }
+    /// Imports each pool in `pools`, as found by `find_importable_pools_with`,
+    /// applying the name, properties and flags in `options`.
+    ///
+    /// Unless `options` forces the import, a pool that `ImportablePool::safety`
+    /// reports as unsafe is refused with `LibZfsError::ImportRefused`.
+    ///
+    /// A `new_name` can only be given when importing a single pool.
+    pub fn import_all_with(
+        &mut self,
+        pools: &[ImportablePool],
+        options: &ImportOptions,
+    ) -> Result<Vec<()>> {
+        if options.new_name.is_some() && pools.len() != 1 {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "a new name can only be given to a single pool, not {}",
+                    pools.len()
+                ),
+            )));
+        }
+
+        let new_name = options
+            .new_name
+            .as_ref()
+            .map(|x| CString::new(x.as_str()).unwrap());
+
+        let props = options.import_props();
+
+        let props = if props.is_empty() {
+            None
+        } else {
+            Some(to_nv_list(&props)?)
+        };
+
+        pools
+            .iter()
+            .map(|x| {
+                if !options.force {
+                    let safety = x.safety()?;
+
+                    if !safety.is_safe() {
+                        return Err(LibZfsError::ImportRefused(x.name.clone(), safety));
+                    }
+                }
 
                 let code = unsafe {
-                    sys::zpool_import(
+                    sys::zpool_import_props(
                         self.raw,
-                        nvl2.as_ptr() as *mut _,
-                        ptr::null(),
-                        ptr::null_mut(),
+                        x.config().as_ptr() as *mut _,
+                        new_name.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+                        props.as_ref().map_or(ptr::null_mut(), |x| x.as_ptr()),
+                        options.flags(),
                     )
                 };
 
                 match code {
                     0 => Ok(()),
-                    x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
+                    _ => Err(last_error(self.raw)),
                 }
             })
             .collect()
     }
     pub fn export_all(&mut self, pools: &[Zpool]) -> Result<Vec<()>> {
+        self.export_all_with(pools, &ExportOptions::default())
+    }
+    /// Exports each pool with the given options,
+    /// stopping at the first failure.
+    pub fn export_all_with(&mut self, pools: &[Zpool], options: &ExportOptions) -> Result<Vec<()>> {
         pools
             .iter()
-            .map(|x| x.disable_datasets().and_then(|_| x.export()))
+            .map(|x| x.export_with(options).map_err(LibZfsError::from))
             .collect()
     }
     pub fn get_imported_pools(&mut self) -> Result<Vec<Zpool>> {
@@ -133,9 +479,287 @@ impl Drop for Libzfs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use test_pool::{file_vdev, TestPool};
 
     #[test]
     fn open_close_handle() {
         Libzfs::new();
     }
+
+    #[test]
+    fn create_mirror_pool() {
+        let fixture = TestPool::new("create_mirror", 2);
+        let mut z = Libzfs::new();
+
+        let pool = z
+            .create_pool(
+                &fixture.name,
+                &fixture.mirror(),
+                &[ZProp {
+                    name: "comment".to_string(),
+                    value: "created by libzfs".to_string(),
+                }],
+                &[],
+            )
+            .expect("could not create pool");
+
+        let children = match pool.vdev_tree().expect("could not get vdev tree") {
+            VDev::Root { children, .. } => children,
+            _ => panic!("did not find root device"),
+        };
+
+        match children[0] {
+            VDev::Mirror { ref children, .. } => assert_eq!(children.len(), 2),
+            _ => panic!("did not find mirror"),
+        };
+    }
+
+    #[test]
+    fn destroy_pool() {
+        let fixture = TestPool::new("destroy_pool", 1);
+        let mut z = Libzfs::new();
+
+        fixture
+            .create(&mut z, &fixture.stripe())
+            .destroy(true, "libzfs test")
+            .expect("could not destroy pool");
+
+        assert!(z.pool_by_name(&fixture.name).is_none());
+    }
+
+    #[test]
+    fn create_pool_invalid_name() {
+        let fixture = TestPool::new("create_invalid", 1);
+        let mut z = Libzfs::new();
+
+        match z.create_pool("mirror", &fixture.stripe(), &[], &[]) {
+            Err(LibZfsError::Zfs(ZfsErrorKind::InvalidName, _)) => {}
+            x => panic!(
+                "expected an invalid name error, got {:?}",
+                x.map(|p| p.name())
+            ),
+        }
+    }
+
+    #[test]
+    fn create_pool_refuses_unlabelled_whole_disk() {
+        let mut z = Libzfs::new();
+
+        let layout = VDev::Root {
+            children: vec![VDev::Disk {
+                guid: None,
+                state: "UNKNOWN".to_string(),
+                path: "/dev/sdz".into(),
+                dev_id: None,
+                phys_path: None,
+                whole_disk: Some(true),
+                is_log: None,
+                alloc_class: None,
+                ashift: None,
+                stats: None,
+            }],
+            spares: vec![],
+            cache: vec![],
+            stats: None,
+        };
+
+        match z.create_pool("whole_disk", &layout, &[], &[]) {
+            Err(LibZfsError::Io(ref e)) if e.kind() == ErrorKind::InvalidInput => {}
+            x => panic!("expected invalid input, got {:?}", x.map(|p| p.name())),
+        }
+    }
+
+    #[test]
+    fn create_raidz2_pool() {
+        let fixture = TestPool::new("create_raidz2", 4);
+        let mut z = Libzfs::new();
+
+        let layout = VDev::Root {
+            children: vec![VDev::RaidZ {
+                children: fixture.vdevs(),
+                nparity: 2,
+                alloc_class: None,
+                ashift: Some(12),
+                stats: None,
+            }],
+            spares: vec![],
+            cache: vec![],
+            stats: None,
+        };
+
+        let pool = fixture.create(&mut z, &layout);
+
+        let children = match pool.vdev_tree().expect("could not get vdev tree") {
+            VDev::Root { children, .. } => children,
+            _ => panic!("did not find root device"),
+        };
+
+        match children[0] {
+            VDev::RaidZ {
+                ref children,
+                nparity,
+                ashift,
+                ..
+            } => {
+                assert_eq!(children.len(), 4);
+                assert_eq!(nparity, 2);
+                assert_eq!(ashift, Some(12));
+            }
+            _ => panic!("did not find raidz"),
+        };
+    }
+
+    #[test]
+    fn snapshot_many_atomically() {
+        let fixture = TestPool::new("snapshot_many", 1);
+        let mut z = Libzfs::new();
+
+        fixture.create(&mut z, &fixture.stripe());
+
+        let snaps = z
+            .snapshot_many(&["snapshot_many@a", "snapshot_many@b"], &[])
+            .expect("could not create snapshots");
+
+        let names: Vec<_> = snaps.iter().map(|x| x.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                CString::new("snapshot_many@a").unwrap(),
+                CString::new("snapshot_many@b").unwrap(),
+            ]
+        );
+
+        match z.snapshot_many(&["snapshot_many@a", "snapshot_many@c"], &[]) {
+            Err(SnapshotError::Failed(errs)) => {
+                assert_eq!(errs.len(), 1);
+                assert_eq!(errs[0].0, "snapshot_many@a");
+            }
+            x => panic!("expected a failed batch, got {:?}", x.map(|_| ())),
+        };
+
+        assert!(z.dataset_by_name("snapshot_many@c").is_none());
+    }
+
+    #[test]
+    fn import_by_guid_with_new_name_readonly() {
+        let fixture = TestPool::new("import_by_guid", 1);
+        let mut z = Libzfs::new();
+
+        let pool = fixture.create(&mut z, &fixture.stripe());
+        let guid = pool.guid();
+
+        pool.export().expect("could not export pool");
+
+        let options = ImportOptions::new()
+            .search_path("/tmp/libzfs-test/import_by_guid")
+            .guid(guid)
+            .new_name("import_renamed")
+            .readonly(true);
+
+        let pools = z
+            .find_importable_pools_with(&options)
+            .expect("could not find pools");
+
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].name, "import_by_guid");
+        assert_eq!(pools[0].guid, guid);
+
+        z.import_all_with(&pools, &options)
+            .expect("could not import pool");
+
+        let pool = z
+            .pool_by_name("import_renamed")
+            .expect("could not find renamed pool");
+
+        assert_eq!(pool.guid(), guid);
+        assert!(pool.read_only());
+    }
+
+    #[test]
+    fn find_importable_pools_by_name_and_guid() {
+        let fixture = TestPool::new("import_filter", 2);
+        let mut z = Libzfs::new();
+
+        for (name, path) in ["import_filter_a", "import_filter_b"]
+            .iter()
+            .zip(&fixture.paths)
+        {
+            let layout = VDev::Root {
+                children: vec![file_vdev(path)],
+                spares: vec![],
+                cache: vec![],
+                stats: None,
+            };
+
+            z.create_pool(name, &layout, &[], &[])
+                .expect("could not create pool")
+                .export()
+                .expect("could not export pool");
+        }
+
+        let search = ImportOptions::new().search_path("/tmp/libzfs-test/import_filter");
+
+        let all = z
+            .find_importable_pools_with(&search)
+            .expect("could not find pools");
+
+        assert_eq!(all.len(), 2);
+
+        let by_name = z
+            .find_importable_pools_with(&search.clone().name("import_filter_a"))
+            .expect("could not find pools");
+
+        assert_eq!(by_name.len(), 1);
+        assert_eq!(by_name[0].name, "import_filter_a");
+
+        let guid = all
+            .iter()
+            .find(|x| x.name == "import_filter_b")
+            .expect("pool not found")
+            .guid;
+
+        let by_guid = z
+            .find_importable_pools_with(&search.guid(guid))
+            .expect("could not find pools");
+
+        assert_eq!(by_guid.len(), 1);
+        assert_eq!(by_guid[0].name, "import_filter_b");
+    }
+
+    #[test]
+    fn import_new_name_needs_single_pool() {
+        let mut z = Libzfs::new();
+        let options = ImportOptions::new().new_name("import_renamed");
+
+        match z.import_all_with(&[], &options) {
+            Err(LibZfsError::Io(ref e)) if e.kind() == ErrorKind::InvalidInput => {}
+            x => panic!("expected invalid input, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn export_hardforce() {
+        let fixture = TestPool::new("export_hardforce", 1);
+        let mut z = Libzfs::new();
+
+        let pool = fixture.create(&mut z, &fixture.stripe());
+
+        let options = ExportOptions {
+            force: true,
+            hardforce: true,
+            log_str: Some("export_hardforce test".to_string()),
+        };
+
+        z.export_all_with(&[pool], &options)
+            .expect("could not export pool");
+
+        assert!(z.pool_by_name(&fixture.name).is_none());
+
+        fixture.import(&mut z).expect("could not import pool");
+
+        z.pool_by_name(&fixture.name)
+            .expect("could not find pool")
+            .destroy(true, "libzfs test")
+            .expect("could not destroy pool");
+    }
 }
@@ -1,16 +1,28 @@
+//! Safe wrappers around libnvpair.
+//!
+//! `NvList` owns an `nvlist_t`, `NvListRef` borrows one, and `NvPair`
+//! borrows a single pair inside a list. Values are added through the
+//! `NvEncode` trait and read back with the `lookup_*` methods or
+//! `NvPair::value`.
+
 extern crate cstr_argument;
 extern crate nvpair_sys as nv_sys;
 
 use self::cstr_argument::CStrArgument;
+pub use self::nv_sys::data_type_t;
 pub use foreign_types::{ForeignType, ForeignTypeRef, Opaque};
+use libzfs_types::nvlist as plain;
 use std::ffi;
+use std::fmt;
 use std::io;
 use std::mem;
 use std::os::raw::{c_int, c_uint};
 use std::ptr;
+use std::slice;
 
+/// A value that can be added to an `NvList` under a name.
 pub trait NvEncode {
-    fn insert<S: CStrArgument>(&self, S, &mut NvListRef) -> io::Result<()>;
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()>;
     //fn read(NvPair &nv) -> io::Result<Self>;
 }
 
@@ -36,11 +48,85 @@ impl NvEncode for bool {
     }
 }
 
-impl NvEncode for u32 {
+macro_rules! impl_nv_encode {
+    ($t:ty, $add:ident) => {
+        impl NvEncode for $t {
+            fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+                let name = name.into_cstr();
+                let v = unsafe { nv_sys::$add(nv.as_mut_ptr(), name.as_ref().as_ptr(), *self) };
+                if v != 0 {
+                    Err(io::Error::from_raw_os_error(v))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+impl_nv_encode!(i8, nvlist_add_int8);
+impl_nv_encode!(u8, nvlist_add_uint8);
+impl_nv_encode!(i16, nvlist_add_int16);
+impl_nv_encode!(u16, nvlist_add_uint16);
+impl_nv_encode!(i32, nvlist_add_int32);
+impl_nv_encode!(u32, nvlist_add_uint32);
+impl_nv_encode!(i64, nvlist_add_int64);
+impl_nv_encode!(u64, nvlist_add_uint64);
+impl_nv_encode!(f64, nvlist_add_double);
+
+macro_rules! impl_nv_encode_array {
+    ($t:ty, $add:ident) => {
+        impl NvEncode for [$t] {
+            fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+                let name = name.into_cstr();
+                let v = unsafe {
+                    nv_sys::$add(
+                        nv.as_mut_ptr(),
+                        name.as_ref().as_ptr(),
+                        self.as_ptr() as *mut _,
+                        self.len() as c_uint,
+                    )
+                };
+                if v != 0 {
+                    Err(io::Error::from_raw_os_error(v))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+impl_nv_encode_array!(i8, nvlist_add_int8_array);
+impl_nv_encode_array!(u8, nvlist_add_uint8_array);
+impl_nv_encode_array!(i16, nvlist_add_int16_array);
+impl_nv_encode_array!(u16, nvlist_add_uint16_array);
+impl_nv_encode_array!(i32, nvlist_add_int32_array);
+impl_nv_encode_array!(u32, nvlist_add_uint32_array);
+impl_nv_encode_array!(i64, nvlist_add_int64_array);
+impl_nv_encode_array!(u64, nvlist_add_uint64_array);
+
+impl NvEncode for [bool] {
     fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let mut xs: Vec<nv_sys::boolean_t> = self
+            .iter()
+            .map(|x| {
+                if *x {
+                    nv_sys::boolean::B_TRUE
+                } else {
+                    nv_sys::boolean::B_FALSE
+                }
+            })
+            .collect();
         let name = name.into_cstr();
-        let v =
-            unsafe { nv_sys::nvlist_add_uint32(nv.as_mut_ptr(), name.as_ref().as_ptr(), *self) };
+        let v = unsafe {
+            nv_sys::nvlist_add_boolean_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                xs.as_mut_ptr(),
+                xs.len() as c_uint,
+            )
+        };
         if v != 0 {
             Err(io::Error::from_raw_os_error(v))
         } else {
@@ -63,6 +149,82 @@ impl NvEncode for ffi::CStr {
     }
 }
 
+impl NvEncode for str {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        to_cstring(self)?.as_c_str().insert(name, nv)
+    }
+}
+
+impl NvEncode for String {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        self.as_str().insert(name, nv)
+    }
+}
+
+impl NvEncode for [&ffi::CStr] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let xs: Vec<_> = self.iter().map(|x| x.as_ptr()).collect();
+        let v = unsafe {
+            nv_sys::nvlist_add_string_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                xs.as_ptr() as *const _,
+                xs.len() as c_uint,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl NvEncode for [ffi::CString] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let xs: Vec<&ffi::CStr> = self.iter().map(|x| x.as_c_str()).collect();
+        xs[..].insert(name, nv)
+    }
+}
+
+impl NvEncode for [&str] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let xs = self
+            .iter()
+            .map(|x| to_cstring(x))
+            .collect::<io::Result<Vec<_>>>()?;
+        xs[..].insert(name, nv)
+    }
+}
+
+impl NvEncode for [String] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let xs = self
+            .iter()
+            .map(|x| to_cstring(x.as_str()))
+            .collect::<io::Result<Vec<_>>>()?;
+        xs[..].insert(name, nv)
+    }
+}
+
+/// Vectors encode as the matching array type.
+///
+/// Note that `Vec::insert` shadows `NvEncode::insert` in method syntax,
+/// so call `NvEncode::insert(&xs, name, nv)` or encode `xs.as_slice()`.
+impl<T> NvEncode for Vec<T>
+where
+    [T]: NvEncode,
+{
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        self.as_slice().insert(name, nv)
+    }
+}
+
+fn to_cstring(s: &str) -> io::Result<ffi::CString> {
+    ffi::CString::new(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
 impl NvEncode for NvListRef {
     fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
         let name = name.into_cstr();
@@ -81,6 +243,47 @@ impl NvEncode for NvListRef {
     }
 }
 
+impl NvEncode for [&NvListRef] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let mut xs: Vec<*mut nv_sys::nvlist> = self.iter().map(|x| x.as_ptr() as *mut _).collect();
+        let v = unsafe {
+            nv_sys::nvlist_add_nvlist_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                xs.as_mut_ptr(),
+                xs.len() as c_uint,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl NvEncode for [NvList] {
+    fn insert<S: CStrArgument>(&self, name: S, nv: &mut NvListRef) -> io::Result<()> {
+        let name = name.into_cstr();
+        let mut xs: Vec<*mut nv_sys::nvlist> = self.iter().map(|x| x.as_ptr()).collect();
+        let v = unsafe {
+            nv_sys::nvlist_add_nvlist_array(
+                nv.as_mut_ptr(),
+                name.as_ref().as_ptr(),
+                xs.as_mut_ptr(),
+                xs.len() as c_uint,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NvEncoding {
     Native,
     Xdr,
@@ -88,9 +291,9 @@ pub enum NvEncoding {
 
 impl NvEncoding {
     fn as_raw(&self) -> c_int {
-        match self {
-            &NvEncoding::Native => nv_sys::NV_ENCODE_NATIVE,
-            &NvEncoding::Xdr => nv_sys::NV_ENCODE_XDR,
+        match *self {
+            NvEncoding::Native => nv_sys::NV_ENCODE_NATIVE,
+            NvEncoding::Xdr => nv_sys::NV_ENCODE_XDR,
         }
     }
 }
@@ -120,9 +323,37 @@ impl NvList {
     }
 
     /// Create a new `NvList` with the `NV_UNIQUE_NAME` constraint
-    pub fn new_unqiue_names() -> io::Result<Self> {
+    ///
+    /// Adding a pair replaces any existing pair with the same name.
+    pub fn new_unique_names() -> io::Result<Self> {
+        Self::with_flags(nv_sys::NV_UNIQUE_NAME)
+    }
+
+    /// Create a new `NvList` with the `NV_UNIQUE_NAME_TYPE` constraint
+    ///
+    /// Adding a pair replaces any existing pair with the same name and type,
+    /// so one name may hold several values of different types.
+    pub fn new_unique_name_types() -> io::Result<Self> {
+        Self::with_flags(nv_sys::NV_UNIQUE_NAME_TYPE)
+    }
+
+    fn with_flags(flags: c_uint) -> io::Result<Self> {
+        let mut n = ptr::null_mut();
+        let v = unsafe { nv_sys::nvlist_alloc(&mut n, flags, 0) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { Self::from_ptr(n) })
+        }
+    }
+
+    /// Rebuild an `NvList` from a buffer produced by `NvListRef::pack`.
+    ///
+    /// The encoding is recorded in the buffer header, so both native
+    /// and XDR buffers are accepted.
+    pub fn unpack(buf: &[u8]) -> io::Result<Self> {
         let mut n = ptr::null_mut();
-        let v = unsafe { nv_sys::nvlist_alloc(&mut n, nv_sys::NV_UNIQUE_NAME, 0) };
+        let v = unsafe { nv_sys::nvlist_unpack(buf.as_ptr() as *mut _, buf.len(), &mut n, 0) };
         if v != 0 {
             Err(io::Error::from_raw_os_error(v))
         } else {
@@ -148,20 +379,27 @@ impl Clone for NvList {
 }
 
 impl NvListRef {
+    /// # Safety
+    ///
+    /// `v` must point to a valid nvlist that outlives `'a`
+    /// and is not otherwise borrowed.
     pub unsafe fn from_mut_ptr<'a>(v: *mut nv_sys::nvlist) -> &'a mut Self {
-        mem::transmute::<*mut nv_sys::nvlist, &mut Self>(v)
+        &mut *(v as *mut Self)
     }
 
+    /// # Safety
+    ///
+    /// `v` must point to a valid nvlist that outlives `'a`.
     pub unsafe fn from_ptr<'a>(v: *const nv_sys::nvlist) -> &'a Self {
-        mem::transmute::<*const nv_sys::nvlist, &Self>(v)
+        &*(v as *const Self)
     }
 
     pub fn as_mut_ptr(&mut self) -> *mut nv_sys::nvlist {
-        unsafe { mem::transmute::<&mut NvListRef, *mut nv_sys::nvlist>(self) }
+        self as *mut NvListRef as *mut nv_sys::nvlist
     }
 
     pub fn as_ptr(&self) -> *const nv_sys::nvlist {
-        unsafe { mem::transmute::<&NvListRef, *const nv_sys::nvlist>(self) }
+        self as *const NvListRef as *const nv_sys::nvlist
     }
 
     pub fn encoded_size(&self, encoding: NvEncoding) -> io::Result<usize> {
@@ -174,6 +412,31 @@ impl NvListRef {
         }
     }
 
+    /// Serialize this list into a byte buffer with the given encoding.
+    ///
+    /// `NvEncoding::Xdr` is portable between hosts, `NvEncoding::Native`
+    /// is only readable on machines with the same endianness.
+    pub fn pack(&self, encoding: NvEncoding) -> io::Result<Vec<u8>> {
+        let mut len = self.encoded_size(encoding)?;
+        let mut buf = vec![0u8; len];
+        let mut p = buf.as_mut_ptr() as *mut _;
+        let v = unsafe {
+            nv_sys::nvlist_pack(
+                self.as_ptr() as *mut _,
+                &mut p,
+                &mut len,
+                encoding.as_raw(),
+                0,
+            )
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            buf.truncate(len);
+            Ok(buf)
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         let v = unsafe { nv_sys::nvlist_empty(self.as_ptr() as *mut _) };
         v != nv_sys::boolean::B_FALSE
@@ -198,7 +461,7 @@ impl NvListRef {
         }
     }
 
-    pub fn iter(&self) -> NvListIter {
+    pub fn iter(&self) -> NvListIter<'_> {
         NvListIter {
             parent: self,
             pos: ptr::null_mut(),
@@ -211,21 +474,69 @@ impl NvListRef {
         v != nv_sys::boolean::B_FALSE
     }
 
-    /*
-    // not allowed because `pair` is borrowed from `self`. Need to fiddle around so that we can
-    // check:
-    //  - `pair` is from `self`
-    //  - `pair` is the only outstanding reference to this pair (need by-value semantics)
-    pub fn remove(&mut self, pair: &NvPair) -> io::Result<()>
-    {
-        let v = unsafe { nv_sys::nvlist_remove_nvpair(self.as_mut_ptr(), pair.as_ptr())};
+    /// Remove every pair called `name`, whatever its type.
+    ///
+    /// Fails with `ENOENT` if there is no such pair.
+    pub fn remove<S: CStrArgument>(&mut self, name: S) -> io::Result<()> {
+        let name = name.into_cstr();
+        let v = unsafe { nv_sys::nvlist_remove_all(self.as_mut_ptr(), name.as_ref().as_ptr()) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Remove the pair called `name` with type `data_type`.
+    ///
+    /// Fails with `ENOENT` if there is no such pair.
+    pub fn remove_type<S: CStrArgument>(
+        &mut self,
+        name: S,
+        data_type: data_type_t::Type,
+    ) -> io::Result<()> {
+        let name = name.into_cstr();
+        let v =
+            unsafe { nv_sys::nvlist_remove(self.as_mut_ptr(), name.as_ref().as_ptr(), data_type) };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Replace any pairs called `name` with `value`.
+    ///
+    /// Unlike `NvEncode::insert`, this does not leave duplicates behind
+    /// in lists created without a uniqueness constraint.
+    pub fn replace<S: CStrArgument, V: NvEncode + ?Sized>(
+        &mut self,
+        name: S,
+        value: &V,
+    ) -> io::Result<()> {
+        let name = name.into_cstr();
+
+        if let Err(e) = self.remove(name.as_ref()) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(e);
+            }
+        }
+
+        value.insert(name.as_ref(), self)
+    }
+
+    /// Copy every pair of `other` into this list with `nvlist_merge`.
+    ///
+    /// Pairs in `other` replace pairs here according to this
+    /// list's uniqueness constraint.
+    pub fn merge(&mut self, other: &NvListRef) -> io::Result<()> {
+        let v = unsafe { nv_sys::nvlist_merge(self.as_mut_ptr(), other.as_ptr() as *mut _, 0) };
         if v != 0 {
             Err(io::Error::from_raw_os_error(v))
         } else {
             Ok(())
         }
     }
-    */
 
     pub fn lookup<S: CStrArgument>(&self, name: S) -> io::Result<&NvPair> {
         let name = name.into_cstr();
@@ -257,13 +568,28 @@ impl NvListRef {
         }
     }
 
-    pub fn lookup_string<S: CStrArgument>(&self, name: S) -> io::Result<ffi::CString> {
+    /// Like `lookup_nv_list`, but borrows the embedded list from `self`
+    /// instead of taking ownership of it.
+    pub fn lookup_nv_list_ref<S: CStrArgument>(&self, name: S) -> io::Result<&NvListRef> {
         let name = name.into_cstr();
-        let mut n;
+
+        let mut n = ptr::null_mut();
 
         let v = unsafe {
-            n = mem::uninitialized();
+            nv_sys::nvlist_lookup_nvlist(self.as_ptr() as *mut _, name.as_ref().as_ptr(), &mut n)
+        };
+        if v != 0 {
+            Err(io::Error::from_raw_os_error(v))
+        } else {
+            Ok(unsafe { NvListRef::from_ptr(n) })
+        }
+    }
 
+    pub fn lookup_string<S: CStrArgument>(&self, name: S) -> io::Result<ffi::CString> {
+        let name = name.into_cstr();
+        let mut n = ptr::null_mut();
+
+        let v = unsafe {
             nv_sys::nvlist_lookup_string(self.as_ptr() as *mut _, name.as_ref().as_ptr(), &mut n)
         };
 
@@ -277,11 +603,9 @@ impl NvListRef {
 
     pub fn lookup_uint64<S: CStrArgument>(&self, name: S) -> io::Result<u64> {
         let name = name.into_cstr();
-        let mut n: u64;
+        let mut n = 0u64;
 
         let v = unsafe {
-            n = mem::uninitialized();
-
             nv_sys::nvlist_lookup_uint64(self.as_ptr() as *mut _, name.as_ref().as_ptr(), &mut n)
         };
         if v != 0 {
@@ -296,10 +620,9 @@ impl NvListRef {
 
         let mut n = ptr::null_mut();
 
-        let mut len: c_uint;
+        let mut len: c_uint = 0;
 
         let v = unsafe {
-            len = mem::uninitialized();
             nv_sys::nvlist_lookup_nvlist_array(
                 self.as_ptr() as *mut _,
                 name.as_ref().as_ptr(),
@@ -326,10 +649,9 @@ impl NvListRef {
 
         let mut n = ptr::null_mut();
 
-        let mut len: c_uint;
+        let mut len: c_uint = 0;
 
         let v = unsafe {
-            len = mem::uninitialized();
             nv_sys::nvlist_lookup_uint64_array(
                 self.as_ptr() as *mut _,
                 name.as_ref().as_ptr(),
@@ -341,17 +663,37 @@ impl NvListRef {
         if v != 0 {
             Err(io::Error::from_raw_os_error(v))
         } else {
-            let r = unsafe {
-                ::std::slice::from_raw_parts(n, len as usize)
-                    .iter()
-                    .map(|x| *x)
-                    .collect()
-            };
+            let r = unsafe { ::std::slice::from_raw_parts(n, len as usize).to_vec() };
 
             Ok(r)
         }
     }
 
+    /// Copy this list into a plain `libzfs_types::nvlist::NvList`,
+    /// which can be used without libnvpair.
+    pub fn to_plain(&self) -> io::Result<plain::NvList> {
+        let (version, flags) = unsafe {
+            let nvl = &*self.as_ptr();
+            (nvl.nvl_version, nvl.nvl_nvflag)
+        };
+
+        let pairs = self
+            .iter()
+            .map(|pair| {
+                Ok(plain::NvPair {
+                    name: to_string(pair.name())?,
+                    value: pair.value()?.to_plain()?,
+                })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        Ok(plain::NvList {
+            version,
+            flags,
+            pairs,
+        })
+    }
+
     pub fn try_to_owned(&self) -> io::Result<NvList> {
         let mut n = NvList(ptr::null_mut());
         let v = unsafe { nv_sys::nvlist_dup(self.as_ptr() as *mut _, &mut n.0, 0) };
@@ -406,3 +748,427 @@ impl NvPair {
         }
     }
 }
+
+impl NvPair {
+    /// The raw `data_type_t` of this pair
+    pub fn data_type(&self) -> nv_sys::data_type_t::Type {
+        unsafe { nv_sys::nvpair_type(self.as_ptr()) }
+    }
+
+    /// Decode the value of this pair into a typed `NvValue`.
+    ///
+    /// Strings, arrays and embedded lists are borrowed from the list that
+    /// owns this pair, so no copies are made.
+    pub fn value(&self) -> io::Result<NvValue<'_>> {
+        let p = self.as_ptr();
+
+        let v = unsafe {
+            match self.data_type() {
+                nv_sys::data_type_t::DATA_TYPE_BOOLEAN => NvValue::Boolean,
+                nv_sys::data_type_t::DATA_TYPE_BOOLEAN_VALUE => {
+                    let x = value_of(|v| nv_sys::nvpair_value_boolean_value(p, v))?;
+                    NvValue::BooleanValue(x != nv_sys::boolean::B_FALSE)
+                }
+                nv_sys::data_type_t::DATA_TYPE_BYTE => {
+                    NvValue::Byte(value_of(|v| nv_sys::nvpair_value_byte(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT8 => {
+                    NvValue::Int8(value_of(|v| nv_sys::nvpair_value_int8(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT8 => {
+                    NvValue::UInt8(value_of(|v| nv_sys::nvpair_value_uint8(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT16 => {
+                    NvValue::Int16(value_of(|v| nv_sys::nvpair_value_int16(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT16 => {
+                    NvValue::UInt16(value_of(|v| nv_sys::nvpair_value_uint16(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT32 => {
+                    NvValue::Int32(value_of(|v| nv_sys::nvpair_value_int32(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT32 => {
+                    NvValue::UInt32(value_of(|v| nv_sys::nvpair_value_uint32(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT64 => {
+                    NvValue::Int64(value_of(|v| nv_sys::nvpair_value_int64(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT64 => {
+                    NvValue::UInt64(value_of(|v| nv_sys::nvpair_value_uint64(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_DOUBLE => {
+                    NvValue::Double(value_of(|v| nv_sys::nvpair_value_double(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_HRTIME => {
+                    NvValue::HrTime(value_of(|v| nv_sys::nvpair_value_hrtime(p, v))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_STRING => {
+                    let s = value_of(|v| nv_sys::nvpair_value_string(p, v))?;
+                    NvValue::String(ffi::CStr::from_ptr(s))
+                }
+                nv_sys::data_type_t::DATA_TYPE_NVLIST => {
+                    let l = value_of(|v| nv_sys::nvpair_value_nvlist(p, v))?;
+                    NvValue::NvList(NvListRef::from_ptr(l))
+                }
+                nv_sys::data_type_t::DATA_TYPE_BOOLEAN_ARRAY => {
+                    let xs = array_of(|v, n| nv_sys::nvpair_value_boolean_array(p, v, n))?;
+                    NvValue::BooleanArray(
+                        xs.iter().map(|x| *x != nv_sys::boolean::B_FALSE).collect(),
+                    )
+                }
+                nv_sys::data_type_t::DATA_TYPE_BYTE_ARRAY => {
+                    NvValue::ByteArray(array_of(|v, n| nv_sys::nvpair_value_byte_array(p, v, n))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT8_ARRAY => {
+                    NvValue::Int8Array(array_of(|v, n| nv_sys::nvpair_value_int8_array(p, v, n))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT8_ARRAY => {
+                    NvValue::UInt8Array(array_of(|v, n| nv_sys::nvpair_value_uint8_array(p, v, n))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT16_ARRAY => {
+                    NvValue::Int16Array(array_of(|v, n| nv_sys::nvpair_value_int16_array(p, v, n))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT16_ARRAY => {
+                    NvValue::UInt16Array(array_of(|v, n| {
+                        nv_sys::nvpair_value_uint16_array(p, v, n)
+                    })?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT32_ARRAY => {
+                    NvValue::Int32Array(array_of(|v, n| nv_sys::nvpair_value_int32_array(p, v, n))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT32_ARRAY => {
+                    NvValue::UInt32Array(array_of(|v, n| {
+                        nv_sys::nvpair_value_uint32_array(p, v, n)
+                    })?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_INT64_ARRAY => {
+                    NvValue::Int64Array(array_of(|v, n| nv_sys::nvpair_value_int64_array(p, v, n))?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_UINT64_ARRAY => {
+                    NvValue::UInt64Array(array_of(|v, n| {
+                        nv_sys::nvpair_value_uint64_array(p, v, n)
+                    })?)
+                }
+                nv_sys::data_type_t::DATA_TYPE_STRING_ARRAY => {
+                    let xs = array_of(|v, n| nv_sys::nvpair_value_string_array(p, v, n))?;
+                    NvValue::StringArray(xs.iter().map(|x| ffi::CStr::from_ptr(*x)).collect())
+                }
+                nv_sys::data_type_t::DATA_TYPE_NVLIST_ARRAY => {
+                    let xs = array_of(|v, n| nv_sys::nvpair_value_nvlist_array(p, v, n))?;
+                    NvValue::NvListArray(xs.iter().map(|x| NvListRef::from_ptr(*x)).collect())
+                }
+                _ => NvValue::Unknown,
+            }
+        };
+
+        Ok(v)
+    }
+}
+
+impl<'a> NvValue<'a> {
+    /// Copy this value into an owned `libzfs_types::nvlist::NvValue`
+    pub fn to_plain(&self) -> io::Result<plain::NvValue> {
+        let v = match *self {
+            NvValue::Unknown => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unknown nvpair data type",
+                ))
+            }
+            NvValue::Boolean => plain::NvValue::Boolean,
+            NvValue::BooleanValue(x) => plain::NvValue::BooleanValue(x),
+            NvValue::Byte(x) => plain::NvValue::Byte(x),
+            NvValue::Int8(x) => plain::NvValue::Int8(x),
+            NvValue::UInt8(x) => plain::NvValue::UInt8(x),
+            NvValue::Int16(x) => plain::NvValue::Int16(x),
+            NvValue::UInt16(x) => plain::NvValue::UInt16(x),
+            NvValue::Int32(x) => plain::NvValue::Int32(x),
+            NvValue::UInt32(x) => plain::NvValue::UInt32(x),
+            NvValue::Int64(x) => plain::NvValue::Int64(x),
+            NvValue::UInt64(x) => plain::NvValue::UInt64(x),
+            NvValue::Double(x) => plain::NvValue::Double(x),
+            NvValue::HrTime(x) => plain::NvValue::HrTime(x),
+            NvValue::String(x) => plain::NvValue::String(to_string(x)?),
+            NvValue::NvList(x) => plain::NvValue::NvList(x.to_plain()?),
+            NvValue::BooleanArray(ref xs) => plain::NvValue::BooleanArray(xs.clone()),
+            NvValue::ByteArray(xs) => plain::NvValue::ByteArray(xs.to_vec()),
+            NvValue::Int8Array(xs) => plain::NvValue::Int8Array(xs.to_vec()),
+            NvValue::UInt8Array(xs) => plain::NvValue::UInt8Array(xs.to_vec()),
+            NvValue::Int16Array(xs) => plain::NvValue::Int16Array(xs.to_vec()),
+            NvValue::UInt16Array(xs) => plain::NvValue::UInt16Array(xs.to_vec()),
+            NvValue::Int32Array(xs) => plain::NvValue::Int32Array(xs.to_vec()),
+            NvValue::UInt32Array(xs) => plain::NvValue::UInt32Array(xs.to_vec()),
+            NvValue::Int64Array(xs) => plain::NvValue::Int64Array(xs.to_vec()),
+            NvValue::UInt64Array(xs) => plain::NvValue::UInt64Array(xs.to_vec()),
+            NvValue::StringArray(ref xs) => plain::NvValue::StringArray(
+                xs.iter().map(|x| to_string(x)).collect::<io::Result<_>>()?,
+            ),
+            NvValue::NvListArray(ref xs) => plain::NvValue::NvListArray(
+                xs.iter().map(|x| x.to_plain()).collect::<io::Result<_>>()?,
+            ),
+        };
+
+        Ok(v)
+    }
+}
+
+fn to_string(s: &ffi::CStr) -> io::Result<String> {
+    s.to_str()
+        .map(String::from)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The decoded value of an `NvPair`, one variant per `data_type_t`.
+///
+/// Borrowed variants live as long as the `NvPair` they were read from.
+#[derive(Debug)]
+pub enum NvValue<'a> {
+    Unknown,
+    /// A name-only flag (`DATA_TYPE_BOOLEAN`); presence is the value
+    Boolean,
+    BooleanValue(bool),
+    Byte(u8),
+    Int8(i8),
+    UInt8(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    HrTime(i64),
+    String(&'a ffi::CStr),
+    NvList(&'a NvListRef),
+    BooleanArray(Vec<bool>),
+    ByteArray(&'a [u8]),
+    Int8Array(&'a [i8]),
+    UInt8Array(&'a [u8]),
+    Int16Array(&'a [i16]),
+    UInt16Array(&'a [u16]),
+    Int32Array(&'a [i32]),
+    UInt32Array(&'a [u32]),
+    Int64Array(&'a [i64]),
+    UInt64Array(&'a [u64]),
+    StringArray(Vec<&'a ffi::CStr>),
+    NvListArray(Vec<&'a NvListRef>),
+}
+
+impl fmt::Debug for NvListRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.iter().map(|p| (p.name(), p.value())))
+            .finish()
+    }
+}
+
+impl fmt::Debug for NvPair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("NvPair")
+            .field(&self.name())
+            .field(&self.value())
+            .finish()
+    }
+}
+
+fn value_of<T, F>(f: F) -> io::Result<T>
+where
+    F: FnOnce(*mut T) -> c_int,
+{
+    let mut v = unsafe { mem::zeroed() };
+    let code = f(&mut v);
+    if code == 0 {
+        Ok(v)
+    } else {
+        Err(io::Error::from_raw_os_error(code))
+    }
+}
+
+fn array_of<'a, T, F>(f: F) -> io::Result<&'a [T]>
+where
+    F: FnOnce(*mut *mut T, *mut c_uint) -> c_int,
+{
+    let mut v = ptr::null_mut();
+    let mut n: c_uint = 0;
+    let code = f(&mut v, &mut n);
+    if code != 0 {
+        Err(io::Error::from_raw_os_error(code))
+    } else if v.is_null() || n == 0 {
+        Ok(&[])
+    } else {
+        Ok(unsafe { slice::from_raw_parts(v, n as usize) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_unpack_round_trip() {
+        let mut nvl = NvList::new_unique_names().unwrap();
+        42u64.insert("guid", &mut nvl).unwrap();
+        "pool".insert("name", &mut nvl).unwrap();
+
+        for encoding in &[NvEncoding::Native, NvEncoding::Xdr] {
+            let buf = nvl.pack(*encoding).unwrap();
+            assert_eq!(buf.len(), nvl.encoded_size(*encoding).unwrap());
+
+            let back = NvList::unpack(&buf).unwrap();
+            assert_eq!(back.lookup_uint64("guid").unwrap(), 42);
+            assert_eq!(back.pack(*encoding).unwrap(), buf);
+        }
+    }
+
+    #[test]
+    fn pure_xdr_matches_libnvpair() {
+        let mut child = NvList::new_unique_names().unwrap();
+        "/dev/sda".insert("path", &mut child).unwrap();
+        (-7i16).insert("i16", &mut child).unwrap();
+
+        let mut nvl = NvList::new_unique_names().unwrap();
+        nvl.add_boolean("flag").unwrap();
+        true.insert("bool", &mut nvl).unwrap();
+        200u8.insert("u8", &mut nvl).unwrap();
+        1.5f64.insert("double", &mut nvl).unwrap();
+        child.insert("child", &mut nvl).unwrap();
+        [1u64, 2, 3][..].insert("u64s", &mut nvl).unwrap();
+        ["a", "bc"][..].insert("strings", &mut nvl).unwrap();
+        [child.clone(), child][..]
+            .insert("children", &mut nvl)
+            .unwrap();
+
+        let buf = nvl.pack(NvEncoding::Xdr).unwrap();
+        let plain = nvl.to_plain().unwrap();
+
+        assert_eq!(plain::NvList::from_xdr(&buf).unwrap(), plain);
+        assert_eq!(plain.to_xdr().unwrap(), buf);
+    }
+
+    fn value_of<'a>(nvl: &'a NvListRef, name: &str) -> NvValue<'a> {
+        nvl.iter()
+            .find(|p| p.name().to_bytes() == name.as_bytes())
+            .expect("pair not found")
+            .value()
+            .expect("could not decode value")
+    }
+
+    macro_rules! assert_value {
+        ($nvl:expr, $name:expr, $p:pat $(if $guard:expr)?) => {
+            match value_of(&$nvl, $name) {
+                $p $(if $guard)? => {}
+                x => panic!("{} decoded as {:?}", $name, x),
+            }
+        };
+    }
+
+    #[test]
+    fn value_round_trip() {
+        let mut child = NvList::new_unique_names().unwrap();
+        "/dev/sda".insert("path", &mut child).unwrap();
+
+        let mut nvl = NvList::new_unique_names().unwrap();
+        nvl.add_boolean("flag").unwrap();
+        true.insert("bool", &mut nvl).unwrap();
+        (-8i8).insert("i8", &mut nvl).unwrap();
+        8u8.insert("u8", &mut nvl).unwrap();
+        (-16i16).insert("i16", &mut nvl).unwrap();
+        16u16.insert("u16", &mut nvl).unwrap();
+        (-32i32).insert("i32", &mut nvl).unwrap();
+        32u32.insert("u32", &mut nvl).unwrap();
+        (-64i64).insert("i64", &mut nvl).unwrap();
+        64u64.insert("u64", &mut nvl).unwrap();
+        1.5f64.insert("double", &mut nvl).unwrap();
+        "pool".insert("string", &mut nvl).unwrap();
+        child.insert("nvlist", &mut nvl).unwrap();
+        [true, false][..].insert("bools", &mut nvl).unwrap();
+        [-1i8, 2][..].insert("i8s", &mut nvl).unwrap();
+        [1u8, 2][..].insert("u8s", &mut nvl).unwrap();
+        [-1i16, 2][..].insert("i16s", &mut nvl).unwrap();
+        [1u16, 2][..].insert("u16s", &mut nvl).unwrap();
+        [-1i32, 2][..].insert("i32s", &mut nvl).unwrap();
+        [1u32, 2][..].insert("u32s", &mut nvl).unwrap();
+        [-1i64, 2][..].insert("i64s", &mut nvl).unwrap();
+        [1u64, 2][..].insert("u64s", &mut nvl).unwrap();
+        ["a", "bc"][..].insert("strings", &mut nvl).unwrap();
+        [child.clone(), child][..]
+            .insert("nvlists", &mut nvl)
+            .unwrap();
+
+        // No `NvEncode` for these, they share Rust types with others
+        let mut bytes = [3u8, 4];
+        let codes = unsafe {
+            [
+                nv_sys::nvlist_add_byte(nvl.as_mut_ptr(), b"byte\0".as_ptr() as *const _, 7),
+                nv_sys::nvlist_add_hrtime(nvl.as_mut_ptr(), b"hrtime\0".as_ptr() as *const _, -9),
+                nv_sys::nvlist_add_byte_array(
+                    nvl.as_mut_ptr(),
+                    b"bytes\0".as_ptr() as *const _,
+                    bytes.as_mut_ptr(),
+                    bytes.len() as c_uint,
+                ),
+            ]
+        };
+        assert_eq!(codes, [0, 0, 0]);
+
+        let buf = nvl.pack(NvEncoding::Xdr).unwrap();
+        let back = NvList::unpack(&buf).unwrap();
+
+        assert_value!(back, "flag", NvValue::Boolean);
+        assert_value!(back, "bool", NvValue::BooleanValue(true));
+        assert_value!(back, "byte", NvValue::Byte(7));
+        assert_value!(back, "i8", NvValue::Int8(-8));
+        assert_value!(back, "u8", NvValue::UInt8(8));
+        assert_value!(back, "i16", NvValue::Int16(-16));
+        assert_value!(back, "u16", NvValue::UInt16(16));
+        assert_value!(back, "i32", NvValue::Int32(-32));
+        assert_value!(back, "u32", NvValue::UInt32(32));
+        assert_value!(back, "i64", NvValue::Int64(-64));
+        assert_value!(back, "u64", NvValue::UInt64(64));
+        assert_value!(back, "double", NvValue::Double(x) if x == 1.5);
+        assert_value!(back, "hrtime", NvValue::HrTime(-9));
+        assert_value!(back, "string", NvValue::String(x) if x.to_bytes() == b"pool");
+        assert_value!(
+            back,
+            "nvlist",
+            NvValue::NvList(x) if x.lookup_string("path").unwrap().to_bytes() == b"/dev/sda"
+        );
+        assert_value!(back, "bools", NvValue::BooleanArray(ref x) if x == &[true, false]);
+        assert_value!(back, "bytes", NvValue::ByteArray(&[3, 4]));
+        assert_value!(back, "i8s", NvValue::Int8Array(&[-1, 2]));
+        assert_value!(back, "u8s", NvValue::UInt8Array(&[1, 2]));
+        assert_value!(back, "i16s", NvValue::Int16Array(&[-1, 2]));
+        assert_value!(back, "u16s", NvValue::UInt16Array(&[1, 2]));
+        assert_value!(back, "i32s", NvValue::Int32Array(&[-1, 2]));
+        assert_value!(back, "u32s", NvValue::UInt32Array(&[1, 2]));
+        assert_value!(back, "i64s", NvValue::Int64Array(&[-1, 2]));
+        assert_value!(back, "u64s", NvValue::UInt64Array(&[1, 2]));
+        assert_value!(
+            back,
+            "strings",
+            NvValue::StringArray(ref x) if x.iter().map(|x| x.to_bytes()).eq(vec![&b"a"[..], b"bc"])
+        );
+        assert_value!(back, "nvlists", NvValue::NvListArray(ref x) if x.len() == 2);
+
+        let plain = nvl.to_plain().unwrap();
+
+        assert_eq!(back.to_plain().unwrap(), plain);
+        assert_eq!(plain::NvList::from_xdr(&buf).unwrap(), plain);
+        assert_eq!(plain.to_xdr().unwrap(), buf);
+    }
+
+    #[test]
+    fn remove_and_replace() {
+        let mut nvl = NvList::new().unwrap();
+        1u64.insert("a", &mut nvl).unwrap();
+        2u64.insert("a", &mut nvl).unwrap();
+
+        nvl.replace("a", &3u64).unwrap();
+        assert_eq!(nvl.iter().count(), 1);
+        assert_eq!(nvl.lookup_uint64("a").unwrap(), 3);
+
+        nvl.remove_type("a", data_type_t::DATA_TYPE_UINT64).unwrap();
+        assert!(!nvl.exists("a"));
+        assert_eq!(nvl.remove("a").unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
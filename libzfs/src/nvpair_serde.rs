@@ -0,0 +1,824 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! Serde support for `NvList`.
+//!
+//! `to_nv_list` turns any `Serialize` struct or map into an `NvList`
+//! that can be handed to libzfs, and `from_nv_list` reads an `NvList`
+//! back into a `Deserialize` type.
+//!
+//! Values map onto nvpair types as follows:
+//!
+//! - `bool` is a `boolean_value`, integers and `f64` use the matching width
+//! - `()` and unit structs are a name-only `boolean` flag
+//! - strings and `char` are strings, `serialize_bytes` is a `byte_array`
+//! - structs and maps are embedded nvlists; map keys must be strings
+//! - sequences and tuples are typed arrays, so every element must have the
+//!   same type. Empty sequences are written as empty `uint64_array`s
+//! - `None` leaves the pair out entirely
+//! - unit enum variants are strings, other variants are a single-pair
+//!   nvlist keyed by the variant name
+//!
+//! When reading, a `boolean` flag deserializes as `true` into a `bool`,
+//! and integers are converted to the requested width when they fit.
+
+extern crate cstr_argument;
+extern crate nvpair_sys as nv_sys;
+extern crate serde;
+
+use self::serde::de::{self, IntoDeserializer};
+use self::serde::ser::{self, Serialize};
+use libzfs_types::{LibZfsError, Result};
+use nvpair::{NvEncode, NvList, NvListIter, NvListRef, NvValue};
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::raw::c_uint;
+use std::vec;
+
+/// Serialize `value` into a new `NvList`.
+///
+/// `value` must serialize as a struct or a map.
+pub fn to_nv_list<T: Serialize + ?Sized>(value: &T) -> Result<NvList> {
+    match value.serialize(Serializer)? {
+        Value::List(nvl) => Ok(nvl),
+        _ => Err(ser::Error::custom(
+            "only structs and maps can be serialized to an NvList",
+        )),
+    }
+}
+
+/// Deserialize a `T` from the pairs of `nvl`.
+pub fn from_nv_list<'de, T: de::Deserialize<'de>>(nvl: &'de NvListRef) -> Result<T> {
+    T::deserialize(Deserializer::new(NvValue::NvList(nvl)))
+}
+
+/// An owned value produced by `Serializer`, waiting for a name
+/// so it can be added to an `NvList`.
+enum Value {
+    Skip,
+    Flag,
+    Bool(bool),
+    Int8(i8),
+    UInt8(u8),
+    Int16(i16),
+    UInt16(u16),
+    Int32(i32),
+    UInt32(u32),
+    Int64(i64),
+    UInt64(u64),
+    Double(f64),
+    String(CString),
+    Bytes(Vec<u8>),
+    List(NvList),
+    Seq(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Skip => "none",
+            Value::Flag => "boolean",
+            Value::Bool(_) => "boolean_value",
+            Value::Int8(_) => "int8",
+            Value::UInt8(_) => "uint8",
+            Value::Int16(_) => "int16",
+            Value::UInt16(_) => "uint16",
+            Value::Int32(_) => "int32",
+            Value::UInt32(_) => "uint32",
+            Value::Int64(_) => "int64",
+            Value::UInt64(_) => "uint64",
+            Value::Double(_) => "double",
+            Value::String(_) => "string",
+            Value::Bytes(_) => "byte_array",
+            Value::List(_) => "nvlist",
+            Value::Seq(_) => "array",
+        }
+    }
+}
+
+fn mixed_array(found: &str) -> LibZfsError {
+    ser::Error::custom(format!(
+        "nvlist arrays must hold a single type, found a {} element",
+        found
+    ))
+}
+
+fn insert(nv: &mut NvListRef, name: &CStr, value: Value) -> Result<()> {
+    match value {
+        Value::Skip => {}
+        Value::Flag => nv.add_boolean(name)?,
+        Value::Bool(x) => x.insert(name, nv)?,
+        Value::Int8(x) => x.insert(name, nv)?,
+        Value::UInt8(x) => x.insert(name, nv)?,
+        Value::Int16(x) => x.insert(name, nv)?,
+        Value::UInt16(x) => x.insert(name, nv)?,
+        Value::Int32(x) => x.insert(name, nv)?,
+        Value::UInt32(x) => x.insert(name, nv)?,
+        Value::Int64(x) => x.insert(name, nv)?,
+        Value::UInt64(x) => x.insert(name, nv)?,
+        Value::Double(x) => x.insert(name, nv)?,
+        Value::String(x) => x.as_c_str().insert(name, nv)?,
+        Value::Bytes(xs) => {
+            let name = name.as_ptr();
+            let code = unsafe {
+                nv_sys::nvlist_add_byte_array(
+                    nv.as_mut_ptr(),
+                    name,
+                    xs.as_ptr() as *mut _,
+                    xs.len() as c_uint,
+                )
+            };
+            if code != 0 {
+                return Err(io::Error::from_raw_os_error(code).into());
+            }
+        }
+        Value::List(x) => x.insert(name, nv)?,
+        Value::Seq(xs) => insert_seq(nv, name, xs)?,
+    };
+
+    Ok(())
+}
+
+fn insert_seq(nv: &mut NvListRef, name: &CStr, items: Vec<Value>) -> Result<()> {
+    macro_rules! add_array {
+        ($variant:ident) => {{
+            let xs = items
+                .into_iter()
+                .map(|x| match x {
+                    Value::$variant(x) => Ok(x),
+                    x => Err(mixed_array(x.type_name())),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            xs[..].insert(name, nv)?
+        }};
+    }
+
+    let kind = match items.first() {
+        Some(x) => x.type_name(),
+        None => "uint64",
+    };
+
+    match kind {
+        "boolean_value" => add_array!(Bool),
+        "int8" => add_array!(Int8),
+        "uint8" => add_array!(UInt8),
+        "int16" => add_array!(Int16),
+        "uint16" => add_array!(UInt16),
+        "int32" => add_array!(Int32),
+        "uint32" => add_array!(UInt32),
+        "int64" => add_array!(Int64),
+        "uint64" => add_array!(UInt64),
+        "string" => add_array!(String),
+        "nvlist" => add_array!(List),
+        x => {
+            return Err(ser::Error::custom(format!(
+                "nvlist arrays cannot hold {} elements",
+                x
+            )))
+        }
+    };
+
+    Ok(())
+}
+
+fn to_cstring<T: Into<Vec<u8>>>(s: T) -> Result<CString> {
+    CString::new(s).map_err(ser::Error::custom)
+}
+
+fn wrap_variant(variant: &str, value: Value) -> Result<Value> {
+    let mut nvl = NvList::new_unique_names()?;
+    insert(&mut nvl, &to_cstring(variant)?, value)?;
+
+    Ok(Value::List(nvl))
+}
+
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = ListSerializer;
+    type SerializeStruct = ListSerializer;
+    type SerializeStructVariant = ListSerializer;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        Ok(Value::Int8(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        Ok(Value::Int16(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        Ok(Value::Int32(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        Ok(Value::Int64(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        Ok(Value::UInt8(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        Ok(Value::UInt16(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        Ok(Value::UInt32(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        Ok(Value::UInt64(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        Ok(Value::Double(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        Ok(Value::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::String(to_cstring(v.to_string())?))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::String(to_cstring(v)?))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Skip)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Flag)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Flag)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        wrap_variant(variant, value.serialize(self)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer> {
+        Ok(SeqSerializer {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqSerializer> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer> {
+        Ok(SeqSerializer {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<ListSerializer> {
+        Ok(ListSerializer {
+            variant: None,
+            list: NvList::new_unique_names()?,
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ListSerializer> {
+        Ok(ListSerializer {
+            variant: Some(variant),
+            list: NvList::new_unique_names()?,
+            key: None,
+        })
+    }
+}
+
+struct SeqSerializer {
+    variant: Option<&'static str>,
+    items: Vec<Value>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.items.push(value.serialize(Serializer)?);
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        match self.variant {
+            Some(variant) => wrap_variant(variant, Value::Seq(self.items)),
+            None => Ok(Value::Seq(self.items)),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+struct ListSerializer {
+    variant: Option<&'static str>,
+    list: NvList,
+    key: Option<CString>,
+}
+
+impl ListSerializer {
+    fn add<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        let value = value.serialize(Serializer)?;
+        insert(&mut self.list, &to_cstring(key)?, value)
+    }
+
+    fn finish(self) -> Result<Value> {
+        match self.variant {
+            Some(variant) => wrap_variant(variant, Value::List(self.list)),
+            None => Ok(Value::List(self.list)),
+        }
+    }
+}
+
+impl ser::SerializeMap for ListSerializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(Serializer)? {
+            Value::String(k) => {
+                self.key = Some(k);
+                Ok(())
+            }
+            x => Err(ser::Error::custom(format!(
+                "nvlist keys must be strings, found a {}",
+                x.type_name()
+            ))),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        match self.key.take() {
+            Some(key) => {
+                let value = value.serialize(Serializer)?;
+                insert(&mut self.list, &key, value)
+            }
+            None => Err(ser::Error::custom(
+                "serialize_value called before serialize_key",
+            )),
+        }
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for ListSerializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.add(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for ListSerializer {
+    type Ok = Value;
+    type Error = LibZfsError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.add(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+/// Deserializes a single `NvValue` borrowed from an `NvList`.
+struct Deserializer<'de> {
+    value: NvValue<'de>,
+}
+
+impl<'de> Deserializer<'de> {
+    fn new(value: NvValue<'de>) -> Self {
+        Deserializer { value }
+    }
+}
+
+fn to_str(s: &CStr) -> Result<&str> {
+    s.to_str().map_err(de::Error::custom)
+}
+
+fn elements<'de>(value: NvValue<'de>) -> Option<Vec<NvValue<'de>>> {
+    let xs = match value {
+        NvValue::BooleanArray(xs) => xs.into_iter().map(NvValue::BooleanValue).collect(),
+        NvValue::ByteArray(xs) => xs.iter().cloned().map(NvValue::Byte).collect(),
+        NvValue::Int8Array(xs) => xs.iter().cloned().map(NvValue::Int8).collect(),
+        NvValue::UInt8Array(xs) => xs.iter().cloned().map(NvValue::UInt8).collect(),
+        NvValue::Int16Array(xs) => xs.iter().cloned().map(NvValue::Int16).collect(),
+        NvValue::UInt16Array(xs) => xs.iter().cloned().map(NvValue::UInt16).collect(),
+        NvValue::Int32Array(xs) => xs.iter().cloned().map(NvValue::Int32).collect(),
+        NvValue::UInt32Array(xs) => xs.iter().cloned().map(NvValue::UInt32).collect(),
+        NvValue::Int64Array(xs) => xs.iter().cloned().map(NvValue::Int64).collect(),
+        NvValue::UInt64Array(xs) => xs.iter().cloned().map(NvValue::UInt64).collect(),
+        NvValue::StringArray(xs) => xs.into_iter().map(NvValue::String).collect(),
+        NvValue::NvListArray(xs) => xs.into_iter().map(NvValue::NvList).collect(),
+        _ => return None,
+    };
+
+    Some(xs)
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = LibZfsError;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            NvValue::Unknown => Err(de::Error::custom(
+                "cannot deserialize an unknown nvpair type",
+            )),
+            NvValue::Boolean => visitor.visit_unit(),
+            NvValue::BooleanValue(x) => visitor.visit_bool(x),
+            NvValue::Byte(x) | NvValue::UInt8(x) => visitor.visit_u8(x),
+            NvValue::Int8(x) => visitor.visit_i8(x),
+            NvValue::Int16(x) => visitor.visit_i16(x),
+            NvValue::UInt16(x) => visitor.visit_u16(x),
+            NvValue::Int32(x) => visitor.visit_i32(x),
+            NvValue::UInt32(x) => visitor.visit_u32(x),
+            NvValue::Int64(x) | NvValue::HrTime(x) => visitor.visit_i64(x),
+            NvValue::UInt64(x) => visitor.visit_u64(x),
+            NvValue::Double(x) => visitor.visit_f64(x),
+            NvValue::String(x) => visitor.visit_borrowed_str(to_str(x)?),
+            NvValue::NvList(x) => visitor.visit_map(MapAccess::new(x)),
+            value => match elements(value) {
+                Some(xs) => visitor.visit_seq(SeqAccess {
+                    iter: xs.into_iter(),
+                }),
+                None => Err(de::Error::custom("cannot deserialize nvpair value")),
+            },
+        }
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            NvValue::Boolean => visitor.visit_bool(true),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            NvValue::ByteArray(xs) | NvValue::UInt8Array(xs) => visitor.visit_borrowed_bytes(xs),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.value {
+            NvValue::String(x) => visitor.visit_enum(to_str(x)?.into_deserializer()),
+            NvValue::NvList(x) => {
+                let mut pairs = x.iter();
+                match (pairs.next(), pairs.next()) {
+                    (Some(pair), None) => visitor.visit_enum(EnumAccess {
+                        variant: to_str(pair.name())?,
+                        value: pair.value()?,
+                    }),
+                    _ => Err(de::Error::custom(
+                        "an enum variant must be an nvlist with exactly one pair",
+                    )),
+                }
+            }
+            ref x => Err(de::Error::custom(format!(
+                "expected an enum variant, found {:?}",
+                x
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+        ignored_any
+    }
+}
+
+struct MapAccess<'de> {
+    iter: NvListIter<'de>,
+    value: Option<NvValue<'de>>,
+}
+
+impl<'de> MapAccess<'de> {
+    fn new(nvl: &'de NvListRef) -> Self {
+        MapAccess {
+            iter: nvl.iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = LibZfsError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some(pair) => {
+                self.value = Some(pair.value()?);
+                let key: de::value::BorrowedStrDeserializer<LibZfsError> =
+                    de::value::BorrowedStrDeserializer::new(to_str(pair.name())?);
+                seed.deserialize(key).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(Deserializer::new(value)),
+            None => Err(de::Error::custom(
+                "next_value_seed called before next_key_seed",
+            )),
+        }
+    }
+}
+
+struct SeqAccess<'de> {
+    iter: vec::IntoIter<NvValue<'de>>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = LibZfsError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(Deserializer::new(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: &'de str,
+    value: NvValue<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = LibZfsError;
+    type Variant = Deserializer<'de>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Deserializer<'de>)> {
+        let variant: de::value::BorrowedStrDeserializer<LibZfsError> =
+            de::value::BorrowedStrDeserializer::new(self.variant);
+        let v = seed.deserialize(variant)?;
+
+        Ok((v, Deserializer::new(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer<'de> {
+    type Error = LibZfsError;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        Plain,
+        Sized(u64),
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        guid: u64,
+        ashift: i32,
+        readonly: bool,
+        comment: Option<String>,
+        children: Vec<BTreeMap<String, u64>>,
+        paths: Vec<String>,
+        kind: Kind,
+        other: Kind,
+    }
+
+    #[test]
+    fn round_trip_struct() {
+        let mut child = BTreeMap::new();
+        child.insert("id".to_string(), 7);
+
+        let config = Config {
+            name: "test".to_string(),
+            guid: 1234,
+            ashift: 12,
+            readonly: true,
+            comment: None,
+            children: vec![child],
+            paths: vec!["/tmp/a".to_string(), "/tmp/b".to_string()],
+            kind: Kind::Plain,
+            other: Kind::Sized(4096),
+        };
+
+        let nvl = to_nv_list(&config).expect("could not serialize");
+
+        assert!(!nvl.exists("comment"));
+        assert_eq!(nvl.lookup_uint64("guid").unwrap(), 1234);
+
+        let back: Config = from_nv_list(&nvl).expect("could not deserialize");
+
+        assert_eq!(back, config);
+    }
+
+    #[test]
+    fn reject_mixed_arrays() {
+        #[derive(Serialize)]
+        struct Mixed {
+            xs: (u64, String),
+        }
+
+        let mixed = Mixed {
+            xs: (1, "a".to_string()),
+        };
+
+        assert!(to_nv_list(&mixed).is_err());
+    }
+}
@@ -8,14 +8,27 @@
 //! Collects into a struct that can be serialized using `serde`.
 //!
 
+extern crate libzfs_sys as sys;
+
 use std::io;
 
 use libzfs::Libzfs;
-use libzfs_types::{Dataset, Pool};
+use libzfs_types::{Dataset, DatasetTree, Pool, Snapshot};
 use libzfs_types::{LibZfsError, Result};
-use zfs::Zfs;
+use zfs::{ListOptions, Zfs};
 use zpool::Zpool;
 
+/// Takes a Zfs reference to a snapshot and converts it into a
+/// `Snapshot`
+fn convert_to_snapshot(x: &Zfs) -> Result<Snapshot> {
+    Ok(Snapshot {
+        name: x.name().into_string()?,
+        creation: x.prop_int(sys::zfs_prop_t_ZFS_PROP_CREATION),
+        used: x.prop_int(sys::zfs_prop_t_ZFS_PROP_USED),
+        referenced: x.prop_int(sys::zfs_prop_t_ZFS_PROP_REFERENCED),
+    })
+}
+
 /// Takes a Zfs reference and converts it into a
 /// `Dataset`
 fn convert_to_dataset(x: &Zfs) -> Result<Dataset> {
@@ -24,14 +37,24 @@ fn convert_to_dataset(x: &Zfs) -> Result<Dataset> {
     let guid = props
         .iter()
         .find(|x| x.name == "guid")
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "could not find guid in props"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "could not find guid in props"))
         .map(|x| x.value.clone())?;
 
+    let snapshots = if x.zfs_type() == sys::zfs_type_t::ZFS_TYPE_SNAPSHOT {
+        vec![]
+    } else {
+        x.snapshots(true)?
+            .iter()
+            .map(convert_to_snapshot)
+            .collect::<Result<_>>()?
+    };
+
     Ok(Dataset {
         name: x.name().into_string()?,
         kind: x.zfs_type_name().into_string()?,
         guid,
         props,
+        snapshots,
     })
 }
 
@@ -62,6 +85,7 @@ fn convert_to_js_pool(p: &Zpool) -> Result<Pool> {
         props: vec![],
         vdev: p.vdev_tree()?,
         datasets: xs,
+        scan: p.scan_status(),
     })
 }
 
@@ -71,7 +95,7 @@ pub fn get_pool_by_name(pool_name: &str) -> Result<Pool> {
     let mut libzfs = Libzfs::new();
 
     libzfs
-        .pool_by_name(&pool_name)
+        .pool_by_name(pool_name)
         .ok_or_else(|| LibZfsError::PoolNotFound(Some(pool_name.to_string()), None))
         .and_then(|x| convert_to_js_pool(&x))
 }
@@ -82,7 +106,7 @@ pub fn get_pool_by_name_and_guid(pool_name: &str, guid: u64) -> Result<Pool> {
     let mut libzfs = Libzfs::new();
 
     libzfs
-        .pool_by_name(&pool_name)
+        .pool_by_name(pool_name)
         .filter(|x| x.guid() == guid)
         .ok_or_else(|| LibZfsError::PoolNotFound(Some(pool_name.to_string()), Some(guid)))
         .and_then(|x| convert_to_js_pool(&x))
@@ -94,11 +118,29 @@ pub fn get_dataset_by_name(ds_name: &str) -> Result<Dataset> {
     let mut libzfs = Libzfs::new();
 
     libzfs
-        .dataset_by_name(&ds_name)
+        .dataset_by_name(ds_name)
         .ok_or_else(|| LibZfsError::ZfsNotFound(ds_name.to_string()))
         .and_then(|x| convert_to_dataset(&x))
 }
 
+/// Given a dataset name, convert it and the filesystems and
+/// volumes below it to a `DatasetTree`.
+/// The `Result` represents failure to find or convert any dataset.
+pub fn get_dataset_tree(ds_name: &str) -> Result<DatasetTree> {
+    let mut libzfs = Libzfs::new();
+
+    let xs = libzfs
+        .dataset_by_name(ds_name)
+        .ok_or_else(|| LibZfsError::ZfsNotFound(ds_name.to_string()))?
+        .list(&ListOptions::default())?
+        .map(|x| x.and_then(|x| convert_to_dataset(&x)))
+        .collect::<Result<Vec<_>>>()?;
+
+    DatasetTree::from_datasets(xs)
+        .pop()
+        .ok_or_else(|| LibZfsError::ZfsNotFound(ds_name.to_string()))
+}
+
 /// Return all imported pools on this node.
 /// Returns `Err` if any imported pool fails conversion to `Pool`.
 pub fn get_imported_pools() -> Result<Vec<Pool>> {
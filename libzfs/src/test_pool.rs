@@ -0,0 +1,170 @@
+// Copyright (c) 2018 DDN. All rights reserved.
+// Use of this source code is governed by a MIT-style
+// license that can be found in the LICENSE file.
+
+//! A throwaway pool on file vdevs, for tests that create,
+//! change or destroy pools.
+//!
+//! Tests that only read the Vagrant "test" pool don't need this.
+//!
+
+use import::ImportOptions;
+use libzfs::Libzfs;
+use libzfs_types::Result;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use vdev::VDev;
+use zpool::Zpool;
+
+lazy_static! {
+    static ref POOLS: Mutex<()> = Mutex::new(());
+}
+
+/// Keeps other tests from adding or removing pools until dropped,
+/// so tests that count pools see a stable set.
+pub fn lock_pools() -> MutexGuard<'static, ()> {
+    POOLS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn file_vdev(path: &Path) -> VDev {
+    VDev::File {
+        guid: None,
+        state: "UNKNOWN".to_string(),
+        path: path.to_path_buf(),
+        is_log: None,
+        alloc_class: None,
+        ashift: None,
+        stats: None,
+    }
+}
+
+fn uses_dir(vdev: &VDev, dir: &Path) -> bool {
+    match *vdev {
+        VDev::File { ref path, .. } | VDev::Disk { ref path, .. } => path.starts_with(dir),
+        VDev::Root {
+            ref children,
+            ref spares,
+            ref cache,
+            ..
+        } => children
+            .iter()
+            .chain(spares)
+            .chain(cache)
+            .any(|x| uses_dir(x, dir)),
+        VDev::Mirror { ref children, .. }
+        | VDev::RaidZ { ref children, .. }
+        | VDev::DRaid { ref children, .. }
+        | VDev::Replacing { ref children, .. }
+        | VDev::Spare { ref children, .. } => children.iter().any(|x| uses_dir(x, dir)),
+        _ => false,
+    }
+}
+
+/// File vdevs for one test pool, in their own directory under `/tmp`.
+///
+/// Holds `lock_pools` for its lifetime. Dropping it destroys every pool
+/// on its files, whether imported or exported and under whatever name,
+/// then removes the files.
+pub struct TestPool {
+    pub name: String,
+    pub paths: Vec<PathBuf>,
+    dir: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestPool {
+    /// Creates `count` 128M files. No pool is created yet.
+    pub fn new(name: &str, count: usize) -> TestPool {
+        let lock = lock_pools();
+        let dir = PathBuf::from(format!("/tmp/libzfs-test/{}", name));
+
+        // Left behind by a run that was killed
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("could not create test pool dir");
+
+        let mut pool = TestPool {
+            name: name.to_string(),
+            paths: vec![],
+            dir,
+            _lock: lock,
+        };
+
+        pool.paths = (0..count)
+            .map(|i| pool.file(&i.to_string(), 128 * 1024 * 1024))
+            .collect();
+
+        pool
+    }
+    /// Creates another file of `size` bytes, not in `paths`.
+    pub fn file(&self, name: &str, size: u64) -> PathBuf {
+        let path = self.dir.join(format!("{}.img", name));
+
+        File::create(&path)
+            .and_then(|f| f.set_len(size))
+            .expect("could not create file vdev");
+
+        path
+    }
+    pub fn vdevs(&self) -> Vec<VDev> {
+        self.paths.iter().map(|x| file_vdev(x)).collect()
+    }
+    /// A layout striping over all the files.
+    pub fn stripe(&self) -> VDev {
+        VDev::Root {
+            children: self.vdevs(),
+            spares: vec![],
+            cache: vec![],
+            stats: None,
+        }
+    }
+    /// A layout with all the files in one mirror.
+    pub fn mirror(&self) -> VDev {
+        VDev::Root {
+            children: vec![VDev::Mirror {
+                children: self.vdevs(),
+                is_log: None,
+                alloc_class: None,
+                ashift: None,
+                stats: None,
+            }],
+            spares: vec![],
+            cache: vec![],
+            stats: None,
+        }
+    }
+    /// Creates the pool called `name` with `layout`.
+    pub fn create(&self, z: &mut Libzfs, layout: &VDev) -> Zpool {
+        z.create_pool(&self.name, layout, &[], &[])
+            .expect("could not create pool")
+    }
+    /// Imports every exported pool on these files.
+    pub fn import(&self, z: &mut Libzfs) -> Result<()> {
+        let options = ImportOptions::new().search_path(&self.dir).force(true);
+        let pools = z.find_importable_pools_with(&options)?;
+
+        z.import_all_with(&pools, &options)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for TestPool {
+    fn drop(&mut self) {
+        let mut z = Libzfs::new();
+
+        let _ = self.import(&mut z);
+
+        if let Ok(pools) = z.get_imported_pools() {
+            for p in pools {
+                let ours = p.vdev_tree().map(|x| uses_dir(&x, &self.dir));
+
+                if ours.unwrap_or(false) {
+                    let _ = p.destroy(true, "libzfs test cleanup");
+                }
+            }
+        }
+
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
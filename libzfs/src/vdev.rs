@@ -5,40 +5,120 @@
 extern crate libzfs_sys as sys;
 
 pub use libzfs_types::VDev;
-use libzfs_types::{LibZfsError, Result};
+use libzfs_types::{AllocationClass, LibZfsError, Result, VDevStats, ZioTypeCounts};
 use nvpair;
-use std::ffi::CStr;
+use nvpair::NvEncode;
+use std::ffi::{CStr, CString};
 use std::io::{Error, ErrorKind};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+fn to_zio_type_counts(xs: &[u64; 6]) -> ZioTypeCounts {
+    ZioTypeCounts {
+        null: xs[0],
+        read: xs[1],
+        write: xs[2],
+        free: xs[3],
+        claim: xs[4],
+        ioctl: xs[5],
+    }
+}
+
+/// Converts a `vdev_stat_t` into `VDevStats`
+pub fn to_vdev_stats(x: &sys::vdev_stat_t) -> VDevStats {
+    VDevStats {
+        timestamp: x.vs_timestamp as u64,
+        alloc: x.vs_alloc,
+        space: x.vs_space,
+        dspace: x.vs_dspace,
+        rsize: x.vs_rsize,
+        esize: x.vs_esize,
+        ops: to_zio_type_counts(&x.vs_ops),
+        bytes: to_zio_type_counts(&x.vs_bytes),
+        read_errors: x.vs_read_errors,
+        write_errors: x.vs_write_errors,
+        checksum_errors: x.vs_checksum_errors,
+        self_healed: x.vs_self_healed,
+        scan_removing: x.vs_scan_removing,
+        scan_processed: x.vs_scan_processed,
+        fragmentation: x.vs_fragmentation,
+    }
+}
 
 pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
+    // Only the root vdev carries the scan stats
+    let resilvering = tree
+        .lookup_uint64_array(sys::zpool_config_scan_stats())
+        .map(sys::to_pool_scan_stat)
+        .map(|x| {
+            x.pss_func == u64::from(sys::pool_scan_func_POOL_SCAN_RESILVER)
+                && x.pss_state == u64::from(sys::dsl_scan_state_DSS_SCANNING)
+        })
+        .unwrap_or(false);
+
+    to_vdev(tree, resilvering)
+}
+
+fn to_vdev(tree: &nvpair::NvList, resilvering: bool) -> Result<VDev> {
     let tmp = tree.lookup_string(sys::zpool_config_type())?;
     let x = tmp.as_bytes_with_nul();
 
-    fn get_children(tree: &nvpair::NvList) -> Result<Vec<VDev>> {
-        tree.lookup_nv_list_array(sys::zpool_config_children())?
-            .iter()
-            .map(enumerate_vdev_tree)
-            .collect()
+    fn to_vdevs(xs: &[nvpair::NvList], resilvering: bool) -> Result<Vec<VDev>> {
+        xs.iter().map(|x| to_vdev(x, resilvering)).collect()
+    }
+
+    fn get_children(tree: &nvpair::NvList, resilvering: bool) -> Result<Vec<VDev>> {
+        to_vdevs(
+            &tree.lookup_nv_list_array(sys::zpool_config_children())?,
+            resilvering,
+        )
     }
 
-    fn get_spares(tree: &nvpair::NvList) -> Result<Vec<VDev>> {
+    fn get_spares(tree: &nvpair::NvList, resilvering: bool) -> Result<Vec<VDev>> {
         let spares = tree.lookup_nv_list_array(sys::zpool_config_spares());
 
         match spares {
-            Ok(x) => x.iter().map(enumerate_vdev_tree).collect(),
+            Ok(x) => to_vdevs(&x, resilvering),
             Err(_) => Ok(vec![]),
         }
     }
 
-    fn get_cache(tree: &nvpair::NvList) -> Result<Vec<VDev>> {
+    fn get_cache(tree: &nvpair::NvList, resilvering: bool) -> Result<Vec<VDev>> {
         let cache = tree.lookup_nv_list_array(sys::zpool_config_l2cache());
 
         match cache {
-            Ok(x) => x.iter().map(enumerate_vdev_tree).collect(),
+            Ok(x) => to_vdevs(&x, resilvering),
             Err(_) => Ok(vec![]),
         }
     }
 
+    /// `zpool replace` always adds the new device as the last child.
+    /// The replacing vdev outlives the resilver until the old device
+    /// is detached, so it only has a new device while resilvering.
+    fn replacing_device(tree: &nvpair::NvList, resilvering: bool) -> Result<Option<usize>> {
+        let n = tree
+            .lookup_nv_list_array(sys::zpool_config_children())?
+            .len();
+
+        if resilvering && n > 1 {
+            Ok(Some(n - 1))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The hot spare is the child marked `is_spare`, during and
+    /// after its resilver.
+    fn spare_device(tree: &nvpair::NvList) -> Result<Option<usize>> {
+        let children = tree.lookup_nv_list_array(sys::zpool_config_children())?;
+
+        Ok(children.iter().position(|x| {
+            x.lookup_uint64(sys::zpool_config_is_spare())
+                .map(|x| x == 1)
+                .unwrap_or(false)
+        }))
+    }
+
     fn lookup_tree_str(tree: &nvpair::NvList, name: String) -> Result<Option<String>> {
         let x = tree.lookup_string(name);
 
@@ -54,14 +134,49 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
             .ok()
     }
 
+    /// Pools from ZFS 0.7 only mark log devices with `is_log`,
+    /// later versions also record an allocation bias.
+    fn lookup_alloc_class(tree: &nvpair::NvList) -> Option<AllocationClass> {
+        let bias = tree
+            .lookup_string(sys::zpool_config_allocation_bias())
+            .ok()
+            .map(|x| x.into_bytes_with_nul());
+
+        match bias {
+            Some(ref x) if x.as_slice() == sys::VDEV_ALLOC_BIAS_LOG => Some(AllocationClass::Log),
+            Some(ref x) if x.as_slice() == sys::VDEV_ALLOC_BIAS_SPECIAL => {
+                Some(AllocationClass::Special)
+            }
+            Some(ref x) if x.as_slice() == sys::VDEV_ALLOC_BIAS_DEDUP => {
+                Some(AllocationClass::Dedup)
+            }
+            _ if lookup_is_log(tree) == Some(true) => Some(AllocationClass::Log),
+            _ => None,
+        }
+    }
+
+    fn lookup_ashift(tree: &nvpair::NvList) -> Option<u64> {
+        tree.lookup_uint64(sys::zpool_config_ashift()).ok()
+    }
+
     fn lookup_guid(tree: &nvpair::NvList) -> Option<u64> {
         tree.lookup_uint64(sys::zpool_config_guid()).ok()
     }
 
+    fn lookup_stats(tree: &nvpair::NvList) -> Option<VDevStats> {
+        tree.lookup_uint64_array(sys::zpool_config_vdev_stats())
+            .map(sys::to_vdev_stat)
+            .map(|x| to_vdev_stats(&x))
+            .ok()
+    }
+
+    /// Configs read from a cachefile or label have no stats to take
+    /// the state from, so their devices are `"UNKNOWN"`.
     fn lookup_state(tree: &nvpair::NvList) -> Result<String> {
-        let vdev_stats = tree
-            .lookup_uint64_array(sys::zpool_config_vdev_stats())
-            .map(sys::to_vdev_stat)?;
+        let vdev_stats = match tree.lookup_uint64_array(sys::zpool_config_vdev_stats()) {
+            Ok(x) => sys::to_vdev_stat(x),
+            Err(_) => return Ok("UNKNOWN".to_string()),
+        };
 
         let state = unsafe {
             let s = sys::zpool_state_to_name(
@@ -74,7 +189,7 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
             CStr::from_ptr(s)
         };
 
-        state.to_owned().into_string().map_err(LibZfsError::from)
+        Ok(state.to_owned().into_string()?)
     }
 
     match x {
@@ -98,6 +213,9 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
                 phys_path,
                 whole_disk,
                 is_log: lookup_is_log(tree),
+                alloc_class: lookup_alloc_class(tree),
+                ashift: lookup_ashift(tree),
+                stats: lookup_stats(tree),
             })
         }
         x if x == sys::VDEV_TYPE_FILE => {
@@ -111,38 +229,99 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
                 state: lookup_state(tree)?,
                 path,
                 is_log: lookup_is_log(tree),
+                alloc_class: lookup_alloc_class(tree),
+                ashift: lookup_ashift(tree),
+                stats: lookup_stats(tree),
             })
         }
         x if x == sys::VDEV_TYPE_MIRROR => {
-            let children = get_children(tree)?;
-            let is_log = tree
-                .lookup_uint64(sys::zpool_config_is_log())
-                .map(|x| x == 1)
-                .ok();
+            let children = get_children(tree, resilvering)?;
 
-            Ok(VDev::Mirror { children, is_log })
+            Ok(VDev::Mirror {
+                children,
+                is_log: lookup_is_log(tree),
+                alloc_class: lookup_alloc_class(tree),
+                ashift: lookup_ashift(tree),
+                stats: lookup_stats(tree),
+            })
         }
         x if x == sys::VDEV_TYPE_RAIDZ => {
-            let children = get_children(tree)?;
+            let children = get_children(tree, resilvering)?;
 
-            Ok(VDev::RaidZ { children })
+            Ok(VDev::RaidZ {
+                children,
+                nparity: tree.lookup_uint64(sys::zpool_config_nparity())?,
+                alloc_class: lookup_alloc_class(tree),
+                ashift: lookup_ashift(tree),
+                stats: lookup_stats(tree),
+            })
         }
+        x if x == sys::VDEV_TYPE_DRAID => {
+            let children = get_children(tree, resilvering)?;
+
+            Ok(VDev::DRaid {
+                children,
+                nparity: tree.lookup_uint64(sys::zpool_config_nparity())?,
+                ndata: tree.lookup_uint64(sys::zpool_config_draid_ndata())?,
+                nspares: tree.lookup_uint64(sys::zpool_config_draid_nspares())?,
+                ngroups: tree.lookup_uint64(sys::zpool_config_draid_ngroups())?,
+                alloc_class: lookup_alloc_class(tree),
+                ashift: lookup_ashift(tree),
+                stats: lookup_stats(tree),
+            })
+        }
+        x if x == sys::VDEV_TYPE_DRAID_SPARE => {
+            let name = tree
+                .lookup_string(sys::zpool_config_path())?
+                .into_string()?;
+
+            Ok(VDev::DistributedSpare {
+                guid: lookup_guid(tree),
+                state: lookup_state(tree)?,
+                name,
+                stats: lookup_stats(tree),
+            })
+        }
+        x if x == sys::VDEV_TYPE_MISSING => Ok(VDev::Missing {
+            guid: lookup_guid(tree),
+        }),
+        x if x == sys::VDEV_TYPE_HOLE => Ok(VDev::Hole),
+        x if x == sys::VDEV_TYPE_INDIRECT => Ok(VDev::Indirect {
+            guid: lookup_guid(tree),
+            stats: lookup_stats(tree),
+        }),
         x if x == sys::VDEV_TYPE_REPLACING => {
-            let children = get_children(tree)?;
+            let children = get_children(tree, resilvering)?;
+            let new_device = replacing_device(tree, resilvering)?;
 
-            Ok(VDev::Replacing { children })
+            Ok(VDev::Replacing {
+                children,
+                new_device,
+                stats: lookup_stats(tree),
+            })
+        }
+        x if x == sys::VDEV_TYPE_SPARE => {
+            let children = get_children(tree, resilvering)?;
+            let new_device = spare_device(tree)?;
+
+            Ok(VDev::Spare {
+                children,
+                new_device,
+                stats: lookup_stats(tree),
+            })
         }
         x if x == sys::VDEV_TYPE_ROOT => {
-            let children = get_children(tree)?;
+            let children = get_children(tree, resilvering)?;
 
-            let spares = get_spares(tree)?;
+            let spares = get_spares(tree, resilvering)?;
 
-            let cache = get_cache(tree)?;
+            let cache = get_cache(tree, resilvering)?;
 
             Ok(VDev::Root {
                 children,
                 spares,
                 cache,
+                stats: lookup_stats(tree),
             })
         }
         _ => Err(LibZfsError::Io(Error::new(
@@ -151,3 +330,273 @@ pub fn enumerate_vdev_tree(tree: &nvpair::NvList) -> Result<VDev> {
         ))),
     }
 }
+
+/// Converts a `VDev` into the config `NvList` libzfs expects
+/// when creating a pool or adding devices to one.
+///
+/// Disks must already be partitioned, `whole_disk: Some(true)` is refused.
+pub fn vdev_to_nv_list(vdev: &VDev) -> Result<nvpair::NvList> {
+    fn to_cstr(x: &[u8]) -> &CStr {
+        CStr::from_bytes_with_nul(x).expect("vdev type was not nul terminated")
+    }
+
+    fn add_type(nvl: &mut nvpair::NvList, x: &[u8]) -> Result<()> {
+        to_cstr(x).insert(sys::zpool_config_type(), nvl)?;
+
+        Ok(())
+    }
+
+    fn add_children(nvl: &mut nvpair::NvList, name: String, children: &[VDev]) -> Result<()> {
+        let xs = children
+            .iter()
+            .map(vdev_to_nv_list)
+            .collect::<Result<Vec<_>>>()?;
+
+        xs.as_slice().insert(name, nvl)?;
+
+        Ok(())
+    }
+
+    fn add_path(nvl: &mut nvpair::NvList, path: &Path) -> Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(Error::from)?;
+
+        path.insert(sys::zpool_config_path(), nvl)?;
+
+        Ok(())
+    }
+
+    /// Logs are marked with `is_log`, which is all ZFS 0.7 understands.
+    /// Other allocation classes are recorded as an allocation bias.
+    fn add_class(
+        nvl: &mut nvpair::NvList,
+        is_log: Option<bool>,
+        alloc_class: Option<AllocationClass>,
+    ) -> Result<()> {
+        let is_log = is_log.unwrap_or(false) || alloc_class == Some(AllocationClass::Log);
+
+        u64::from(is_log).insert(sys::zpool_config_is_log(), nvl)?;
+
+        let bias = match alloc_class {
+            Some(AllocationClass::Special) => Some(sys::VDEV_ALLOC_BIAS_SPECIAL as &[u8]),
+            Some(AllocationClass::Dedup) => Some(sys::VDEV_ALLOC_BIAS_DEDUP as &[u8]),
+            _ => None,
+        };
+
+        if let Some(x) = bias {
+            to_cstr(x).insert(sys::zpool_config_allocation_bias(), nvl)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_ashift(nvl: &mut nvpair::NvList, ashift: Option<u64>) -> Result<()> {
+        if let Some(x) = ashift {
+            x.insert(sys::zpool_config_ashift(), nvl)?;
+        }
+
+        Ok(())
+    }
+
+    let mut nvl = nvpair::NvList::new_unique_names()?;
+
+    match *vdev {
+        VDev::Root {
+            ref children,
+            ref spares,
+            ref cache,
+            ..
+        } => {
+            add_type(&mut nvl, sys::VDEV_TYPE_ROOT)?;
+            add_children(&mut nvl, sys::zpool_config_children(), children)?;
+
+            if !spares.is_empty() {
+                add_children(&mut nvl, sys::zpool_config_spares(), spares)?;
+            }
+
+            if !cache.is_empty() {
+                add_children(&mut nvl, sys::zpool_config_l2cache(), cache)?;
+            }
+        }
+        VDev::Mirror {
+            ref children,
+            is_log,
+            alloc_class,
+            ashift,
+            ..
+        } => {
+            add_type(&mut nvl, sys::VDEV_TYPE_MIRROR)?;
+            add_children(&mut nvl, sys::zpool_config_children(), children)?;
+            add_class(&mut nvl, is_log, alloc_class)?;
+            add_ashift(&mut nvl, ashift)?;
+        }
+        VDev::RaidZ {
+            ref children,
+            nparity,
+            alloc_class,
+            ashift,
+            ..
+        } => {
+            add_type(&mut nvl, sys::VDEV_TYPE_RAIDZ)?;
+            nparity.insert(sys::zpool_config_nparity(), &mut nvl)?;
+            add_children(&mut nvl, sys::zpool_config_children(), children)?;
+            add_class(&mut nvl, None, alloc_class)?;
+            add_ashift(&mut nvl, ashift)?;
+        }
+        VDev::Disk {
+            ref path,
+            whole_disk,
+            is_log,
+            alloc_class,
+            ashift,
+            ..
+        } => {
+            // `zpool create` writes an EFI label before it sets whole_disk,
+            // which libzfs leaves to its callers.
+            if whole_disk == Some(true) {
+                return Err(LibZfsError::Io(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "{} must be partitioned first, whole disks are not labelled",
+                        path.display()
+                    ),
+                )));
+            }
+
+            add_type(&mut nvl, sys::VDEV_TYPE_DISK)?;
+            add_path(&mut nvl, path)?;
+            0u64.insert(sys::zpool_config_whole_disk(), &mut nvl)?;
+            add_class(&mut nvl, is_log, alloc_class)?;
+            add_ashift(&mut nvl, ashift)?;
+        }
+        VDev::File {
+            ref path,
+            is_log,
+            alloc_class,
+            ashift,
+            ..
+        } => {
+            add_type(&mut nvl, sys::VDEV_TYPE_FILE)?;
+            add_path(&mut nvl, path)?;
+            add_class(&mut nvl, is_log, alloc_class)?;
+            add_ashift(&mut nvl, ashift)?;
+        }
+        VDev::DRaid { .. } => {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                "draid vdevs are not supported by ZFS 0.7",
+            )))
+        }
+        VDev::Replacing { .. }
+        | VDev::Spare { .. }
+        | VDev::DistributedSpare { .. }
+        | VDev::Missing { .. }
+        | VDev::Hole
+        | VDev::Indirect { .. } => {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                "vdevs of this type cannot be created directly",
+            )))
+        }
+    }
+
+    Ok(nvl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    fn with_type(x: &[u8]) -> nvpair::NvList {
+        let mut nvl = nvpair::NvList::new_unique_names().unwrap();
+
+        CStr::from_bytes_with_nul(x)
+            .unwrap()
+            .insert(sys::zpool_config_type(), &mut nvl)
+            .unwrap();
+
+        nvl
+    }
+
+    fn file(path: &str, is_spare: bool) -> nvpair::NvList {
+        let mut nvl = with_type(sys::VDEV_TYPE_FILE);
+        let stats = vec![0u64; mem::size_of::<sys::vdev_stat_t>() / 8];
+
+        path.insert(sys::zpool_config_path(), &mut nvl).unwrap();
+        stats
+            .insert(sys::zpool_config_vdev_stats(), &mut nvl)
+            .unwrap();
+
+        if is_spare {
+            1u64.insert(sys::zpool_config_is_spare(), &mut nvl).unwrap();
+        }
+
+        nvl
+    }
+
+    fn root(vdev: nvpair::NvList, scan: Option<sys::pool_scan_func>) -> nvpair::NvList {
+        let mut nvl = with_type(sys::VDEV_TYPE_ROOT);
+
+        vec![vdev]
+            .insert(sys::zpool_config_children(), &mut nvl)
+            .unwrap();
+
+        if let Some(func) = scan {
+            let mut xs = vec![0u64; mem::size_of::<sys::pool_scan_stat_t>() / 8];
+
+            xs[0] = u64::from(func);
+            xs[1] = u64::from(sys::dsl_scan_state_DSS_SCANNING);
+
+            xs.insert(sys::zpool_config_scan_stats(), &mut nvl).unwrap();
+        }
+
+        nvl
+    }
+
+    fn new_device(tree: &nvpair::NvList) -> Option<usize> {
+        match enumerate_vdev_tree(tree).expect("could not read vdev tree") {
+            VDev::Root { children, .. } => match children[0] {
+                VDev::Replacing { new_device, .. } | VDev::Spare { new_device, .. } => new_device,
+                ref x => panic!("unexpected vdev {:?}", x),
+            },
+            x => panic!("unexpected vdev {:?}", x),
+        }
+    }
+
+    fn replacing() -> nvpair::NvList {
+        let mut nvl = with_type(sys::VDEV_TYPE_REPLACING);
+
+        vec![file("/tmp/old.img", false), file("/tmp/new.img", false)]
+            .insert(sys::zpool_config_children(), &mut nvl)
+            .unwrap();
+
+        nvl
+    }
+
+    #[test]
+    fn replacing_while_resilvering() {
+        let tree = root(replacing(), Some(sys::pool_scan_func_POOL_SCAN_RESILVER));
+
+        assert_eq!(new_device(&tree), Some(1));
+    }
+
+    #[test]
+    fn replacing_after_resilver() {
+        assert_eq!(new_device(&root(replacing(), None)), None);
+
+        let tree = root(replacing(), Some(sys::pool_scan_func_POOL_SCAN_SCRUB));
+
+        assert_eq!(new_device(&tree), None);
+    }
+
+    #[test]
+    fn spare_after_resilver() {
+        let mut spare = with_type(sys::VDEV_TYPE_SPARE);
+
+        vec![file("/tmp/spare.img", true), file("/tmp/failed.img", false)]
+            .insert(sys::zpool_config_children(), &mut spare)
+            .unwrap();
+
+        assert_eq!(new_device(&root(spare, None)), Some(0));
+    }
+}
@@ -4,12 +4,207 @@
 
 extern crate libzfs_sys as sys;
 
-use libzfs_types::{LibZfsError, Result};
+use libzfs::last_error;
+use libzfs_types::{
+    LibZfsError, Result, RollbackReport, SnapshotError, SnapshotResult, ZfsErrorKind,
+};
 use nvpair;
 use std::ffi::{CStr, CString};
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_int, c_void};
 use std::ptr;
-use zprop_list::{ZProp, ZpropItem, ZpropList};
+use zprop_list::{to_nv_list, ZProp, ZpropItem, ZpropList};
+
+/// Opens the dataset named `name` if it is one of `types`.
+pub(crate) fn open(
+    hdl: *mut sys::libzfs_handle_t,
+    name: &str,
+    types: sys::zfs_type_t,
+) -> Result<Zfs> {
+    let sys::zfs_type_t(zfs_type) = types;
+    let name = CString::new(name).unwrap();
+
+    let x = unsafe { sys::zfs_open(hdl, name.as_ptr(), zfs_type as c_int) };
+
+    if x.is_null() {
+        Err(last_error(hdl))
+    } else {
+        Ok(Zfs::new(x))
+    }
+}
+
+fn has_type(x: sys::zfs_type_t, types: sys::zfs_type_t) -> bool {
+    let sys::zfs_type_t(x) = x & types;
+
+    x != 0
+}
+
+/// Collects the handles a `zfs_iter_*` function passes to its callback.
+fn collect_handles<F>(f: F) -> Result<Vec<Zfs>>
+where
+    F: FnOnce(sys::zfs_iter_f, *mut c_void) -> c_int,
+{
+    unsafe extern "C" fn callback(handle: *mut sys::zfs_handle_t, state: *mut c_void) -> c_int {
+        let state = &mut *(state as *mut Vec<Zfs>);
+
+        state.push(Zfs::new(handle));
+
+        0
+    }
+
+    let mut state: Vec<Zfs> = Vec::new();
+    let state_ptr: *mut c_void = &mut state as *mut _ as *mut c_void;
+    let code = f(Some(callback), state_ptr);
+
+    match code {
+        0 => Ok(state),
+        x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
+    }
+}
+
+/// Dataset types to return from a listing, like `zfs list -t`.
+///
+/// The default is filesystems and volumes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DatasetTypes {
+    pub filesystems: bool,
+    pub volumes: bool,
+    pub snapshots: bool,
+    pub bookmarks: bool,
+}
+
+impl Default for DatasetTypes {
+    fn default() -> Self {
+        DatasetTypes {
+            filesystems: true,
+            volumes: true,
+            snapshots: false,
+            bookmarks: false,
+        }
+    }
+}
+
+impl DatasetTypes {
+    fn bits(&self) -> sys::zfs_type_t {
+        let mut types = sys::zfs_type_t(0);
+
+        if self.filesystems {
+            types |= sys::zfs_type_t::ZFS_TYPE_FILESYSTEM;
+        }
+
+        if self.volumes {
+            types |= sys::zfs_type_t::ZFS_TYPE_VOLUME;
+        }
+
+        if self.snapshots {
+            types |= sys::zfs_type_t::ZFS_TYPE_SNAPSHOT;
+        }
+
+        if self.bookmarks {
+            types |= sys::zfs_type_t::ZFS_TYPE_BOOKMARK;
+        }
+
+        types
+    }
+}
+
+/// Options for listing a dataset and its descendants, like `zfs list -r`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ListOptions {
+    /// How many levels below the starting dataset to descend,
+    /// like `zfs list -d`. `None` descends all the way.
+    pub depth: Option<usize>,
+    /// Which types to return. Filesystems and volumes are
+    /// still descended into when they are not returned.
+    pub types: DatasetTypes,
+}
+
+/// Options for rolling a dataset back to a snapshot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RollbackOptions {
+    /// Unmount the filesystem even if it is in use, like `zfs rollback -f`.
+    pub force: bool,
+    /// Destroy snapshots and bookmarks newer than the target,
+    /// like `zfs rollback -r`.
+    pub destroy_newer: bool,
+    /// Also destroy clones of the newer snapshots, like `zfs rollback -R`.
+    /// Implies `destroy_newer`.
+    pub destroy_clones: bool,
+}
+
+fn names(xs: &[Zfs]) -> Result<Vec<String>> {
+    xs.iter()
+        .map(|x| x.name().into_string().map_err(LibZfsError::from))
+        .collect()
+}
+
+/// Walks a dataset and its descendants depth first, parents first.
+///
+/// A dataset's snapshots, oldest first, and bookmarks come
+/// before its children. Children are read as their parent is reached.
+/// If they can't be read an `Err` is yielded in place of the parent
+/// and its subtree is skipped.
+pub struct DatasetIter {
+    stack: Vec<(Zfs, usize)>,
+    options: ListOptions,
+}
+
+impl DatasetIter {
+    pub(crate) fn new(root: Zfs, options: &ListOptions) -> DatasetIter {
+        DatasetIter {
+            stack: vec![(root, 0)],
+            options: *options,
+        }
+    }
+    fn children(&self, ds: &Zfs) -> Result<Vec<Zfs>> {
+        let mut xs = vec![];
+
+        if self.options.types.snapshots {
+            xs.extend(ds.snapshots(true)?);
+        }
+
+        if self.options.types.bookmarks {
+            xs.extend(ds.bookmarks()?);
+        }
+
+        xs.extend(ds.children()?);
+
+        Ok(xs)
+    }
+}
+
+impl Iterator for DatasetIter {
+    type Item = Result<Zfs>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((ds, depth)) = self.stack.pop() {
+            let within_depth = match self.options.depth {
+                Some(d) => depth < d,
+                None => true,
+            };
+
+            let expand = has_type(
+                ds.zfs_type(),
+                sys::zfs_type_t::ZFS_TYPE_FILESYSTEM | sys::zfs_type_t::ZFS_TYPE_VOLUME,
+            ) && within_depth;
+
+            if expand {
+                match self.children(&ds) {
+                    Ok(xs) => self
+                        .stack
+                        .extend(xs.into_iter().rev().map(|x| (x, depth + 1))),
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+
+            if has_type(ds.zfs_type(), self.options.types.bits()) {
+                return Some(Ok(ds));
+            }
+        }
+
+        None
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Zfs {
@@ -57,7 +252,7 @@ impl Zfs {
             x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
         }
     }
-    pub fn props(&self) -> Result<(Vec<ZProp>)> {
+    pub fn props(&self) -> Result<Vec<ZProp>> {
         let buff_size = 319;
         let pl = self.prop_list()?;
 
@@ -106,6 +301,204 @@ impl Zfs {
 
         Ok(xs)
     }
+    /// Gets a numeric property, such as `used` or `creation`.
+    pub fn prop_int(&self, prop: sys::zfs_prop_t) -> u64 {
+        unsafe { sys::zfs_prop_get_int(self.raw, prop) }
+    }
+    /// Lists the snapshots of this dataset, like `zfs list -t snapshot -d 1`.
+    ///
+    /// If `sorted` is set they are returned oldest first, by `createtxg`.
+    /// Otherwise they come in the order ZFS stores them.
+    pub fn snapshots(&self, sorted: bool) -> Result<Vec<Zfs>> {
+        collect_handles(|callback, state| unsafe {
+            if sorted {
+                sys::zfs_iter_snapshots_sorted(self.raw, callback, state)
+            } else {
+                sys::zfs_iter_snapshots(self.raw, sys::boolean::B_FALSE, callback, state)
+            }
+        })
+    }
+    /// Lists the bookmarks of this dataset.
+    pub fn bookmarks(&self) -> Result<Vec<Zfs>> {
+        collect_handles(|callback, state| unsafe {
+            sys::zfs_iter_bookmarks(self.raw, callback, state)
+        })
+    }
+    /// Lists the filesystems and volumes directly below this one.
+    pub fn children(&self) -> Result<Vec<Zfs>> {
+        collect_handles(|callback, state| unsafe {
+            sys::zfs_iter_filesystems(self.raw, callback, state)
+        })
+    }
+    /// Walks this dataset and its descendants, like `zfs list -r`.
+    ///
+    /// This dataset is included when its type is in `options.types`.
+    ///
+    /// The walk starts from a new handle opened by this dataset's name,
+    /// so it fails with `ZfsErrorKind::NoEnt` if the dataset has since
+    /// been destroyed or renamed.
+    pub fn list(&self, options: &ListOptions) -> Result<DatasetIter> {
+        let hdl = unsafe { sys::zfs_get_handle(self.raw) };
+        let root = open(hdl, &self.name().to_string_lossy(), self.zfs_type())?;
+
+        Ok(DatasetIter::new(root, options))
+    }
+    /// Rolls this dataset back to `snapshot`, like `zfs rollback`.
+    ///
+    /// Fails if newer snapshots or bookmarks exist,
+    /// use `rollback_with` to destroy them.
+    pub fn rollback(&self, snapshot: &Zfs, force: bool) -> Result<RollbackReport> {
+        self.rollback_with(
+            snapshot,
+            &RollbackOptions {
+                force,
+                ..RollbackOptions::default()
+            },
+        )
+    }
+    /// Rolls this dataset back to `snapshot`, destroying anything
+    /// newer that `options` allows.
+    ///
+    /// `zfs_rollback` destroys everything newer than the target
+    /// unconditionally, so it is checked for here first. Anything created
+    /// between that check and the rollback is destroyed without being
+    /// reported.
+    pub fn rollback_with(
+        &self,
+        snapshot: &Zfs,
+        options: &RollbackOptions,
+    ) -> Result<RollbackReport> {
+        let hdl = unsafe { sys::zfs_get_handle(self.raw) };
+        let name = self.name().into_string()?;
+        let target = snapshot.name().into_string()?;
+
+        if snapshot.zfs_type() != sys::zfs_type_t::ZFS_TYPE_SNAPSHOT
+            || !target.starts_with(&format!("{}@", name))
+        {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a snapshot of {}", target, name),
+            )));
+        }
+
+        let txg = snapshot.prop_int(sys::zfs_prop_t_ZFS_PROP_CREATETXG);
+        let newer = |x: &Zfs| x.prop_int(sys::zfs_prop_t_ZFS_PROP_CREATETXG) > txg;
+
+        let snapshots: Vec<Zfs> = self.snapshots(true)?.into_iter().filter(&newer).collect();
+        let bookmarks: Vec<Zfs> = self.bookmarks()?.into_iter().filter(&newer).collect();
+
+        let mut clones = vec![];
+
+        for snap in &snapshots {
+            clones.extend(collect_handles(|callback, state| unsafe {
+                sys::zfs_iter_dependents(snap.raw, sys::boolean::B_FALSE, callback, state)
+            })?);
+        }
+
+        let report = RollbackReport {
+            snapshots: names(&snapshots)?,
+            bookmarks: names(&bookmarks)?,
+            clones: names(&clones)?,
+        };
+
+        let destroy_newer = options.destroy_newer || options.destroy_clones;
+        let has_newer = report.snapshots.len() + report.bookmarks.len() > 0;
+
+        if has_newer && !destroy_newer {
+            return Err(LibZfsError::Zfs(
+                ZfsErrorKind::Exists,
+                format!(
+                    "more recent snapshots or bookmarks exist: {}",
+                    report
+                        .snapshots
+                        .iter()
+                        .chain(report.bookmarks.iter())
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ));
+        }
+
+        if !options.destroy_clones && !report.clones.is_empty() {
+            return Err(LibZfsError::Zfs(
+                ZfsErrorKind::Exists,
+                format!(
+                    "clones of more recent snapshots exist: {}",
+                    report.clones.join(", ")
+                ),
+            ));
+        }
+
+        let force = if options.force {
+            sys::boolean::B_TRUE
+        } else {
+            sys::boolean::B_FALSE
+        };
+
+        let code = unsafe { sys::zfs_rollback(self.raw, snapshot.raw, force) };
+
+        match code {
+            0 => Ok(report),
+            _ => Err(last_error(hdl)),
+        }
+    }
+    /// Takes a snapshot of this dataset named `<dataset>@<name>`, like `zfs snapshot`.
+    ///
+    /// If `recursive` is set, all descendant datasets are snapshotted
+    /// atomically as well. `props` are set on each new snapshot.
+    /// Returns the new snapshots, this dataset's first.
+    pub fn snapshot(
+        &self,
+        name: &str,
+        recursive: bool,
+        props: &[ZProp],
+    ) -> SnapshotResult<Vec<Zfs>> {
+        let hdl = unsafe { sys::zfs_get_handle(self.raw) };
+
+        let props = if props.is_empty() {
+            None
+        } else {
+            Some(to_nv_list(props).map_err(SnapshotError::Other)?)
+        };
+
+        let full_name = format!("{}@{}", self.name().to_string_lossy(), name);
+        let snap_name = CString::new(full_name.as_str()).unwrap();
+
+        let code = unsafe {
+            sys::zfs_snapshot(
+                hdl,
+                snap_name.as_ptr(),
+                if recursive {
+                    sys::boolean::B_TRUE
+                } else {
+                    sys::boolean::B_FALSE
+                },
+                props
+                    .as_ref()
+                    .map_or(ptr::null_mut(), |x| x.as_ptr() as *mut _),
+            )
+        };
+
+        if code != 0 {
+            return Err(SnapshotError::Other(last_error(hdl)));
+        }
+
+        let options = ListOptions {
+            depth: if recursive { None } else { Some(0) },
+            ..ListOptions::default()
+        };
+
+        self.list(&options)
+            .map_err(SnapshotError::Other)?
+            .map(|ds| {
+                let snap = format!("{}@{}", ds?.name().to_string_lossy(), name);
+
+                open(hdl, &snap, sys::zfs_type_t::ZFS_TYPE_SNAPSHOT)
+            })
+            .collect::<Result<_>>()
+            .map_err(SnapshotError::Other)
+    }
 }
 
 impl Drop for Zfs {
@@ -121,15 +514,18 @@ mod tests {
     use std::ffi::CString;
     use std::panic;
     use std::str;
+    use test_pool::TestPool;
     use zprop_list::ZProp;
 
-    fn zfs_by_name<F: Fn(&Zfs) -> ()>(name: &str, f: F) -> ()
+    fn zfs_by_name<F>(name: &str, f: F)
     where
-        F: panic::RefUnwindSafe,
+        F: Fn(&Zfs) + panic::RefUnwindSafe,
     {
         let mut z = Libzfs::new();
 
-        let pools_to_import = z.find_importable_pools();
+        let pools_to_import = z
+            .find_importable_pools()
+            .expect("could not find importable pools");
 
         z.import_all(&pools_to_import)
             .expect("Could not import pools");
@@ -159,6 +555,38 @@ mod tests {
         });
     }
 
+    #[test]
+    fn list_recursive() {
+        zfs_by_name("test", |ds| {
+            let names = |options: &ListOptions| -> Vec<String> {
+                ds.list(options)
+                    .expect("could not list datasets")
+                    .map(|x| x.unwrap().name().into_string().unwrap())
+                    .collect()
+            };
+
+            let all = names(&ListOptions::default());
+            assert_eq!(&all[..2], &["test".to_string(), "test/ds".to_string()]);
+
+            let top = names(&ListOptions {
+                depth: Some(0),
+                ..ListOptions::default()
+            });
+            assert_eq!(top, vec!["test".to_string()]);
+
+            let snapshots = names(&ListOptions {
+                depth: None,
+                types: DatasetTypes {
+                    filesystems: false,
+                    volumes: false,
+                    snapshots: true,
+                    bookmarks: false,
+                },
+            });
+            assert!(snapshots.iter().all(|x| x.contains('@')));
+        });
+    }
+
     #[test]
     fn dataset_props() {
         zfs_by_name("test/ds", |ds| {
@@ -432,4 +860,85 @@ mod tests {
             )
         });
     }
+
+    #[test]
+    fn snapshot_and_list_snapshots() {
+        let fixture = TestPool::new("create_snapshots", 1);
+        let mut z = Libzfs::new();
+
+        fixture.create(&mut z, &fixture.stripe());
+
+        let root = z
+            .dataset_by_name(&fixture.name)
+            .expect("could not get root dataset");
+
+        for name in &["a", "b", "c"] {
+            let snaps = root
+                .snapshot(name, true, &[])
+                .expect("could not create snapshot");
+
+            assert_eq!(
+                snaps[0].name(),
+                CString::new(format!("create_snapshots@{}", name)).unwrap()
+            );
+        }
+
+        let names: Vec<_> = root
+            .snapshots(true)
+            .expect("could not list snapshots")
+            .iter()
+            .map(|x| x.name().into_string().unwrap())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "create_snapshots@a",
+                "create_snapshots@b",
+                "create_snapshots@c",
+            ]
+        );
+    }
+
+    #[test]
+    fn rollback_to_snapshot() {
+        let fixture = TestPool::new("rollback", 1);
+        let mut z = Libzfs::new();
+
+        fixture.create(&mut z, &fixture.stripe());
+
+        let root = z
+            .dataset_by_name(&fixture.name)
+            .expect("could not get root dataset");
+
+        let a = root
+            .snapshot("a", false, &[])
+            .expect("could not create snapshot")
+            .remove(0);
+
+        root.snapshot("b", false, &[])
+            .expect("could not create snapshot");
+
+        match root.rollback(&a, false) {
+            Err(LibZfsError::Zfs(ZfsErrorKind::Exists, _)) => {}
+            x => panic!("expected newer snapshots to block rollback, got {:?}", x),
+        };
+
+        let report = root
+            .rollback_with(
+                &a,
+                &RollbackOptions {
+                    destroy_newer: true,
+                    ..RollbackOptions::default()
+                },
+            )
+            .expect("could not roll back");
+
+        assert_eq!(report.snapshots, vec!["rollback@b".to_string()]);
+        assert!(report.clones.is_empty());
+        assert!(z.dataset_by_name("rollback@b").is_none());
+
+        root.rollback(&a, false)
+            .expect("could not roll back to latest snapshot");
+    }
 }
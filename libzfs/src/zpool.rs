@@ -4,14 +4,233 @@
 
 extern crate libzfs_sys as sys;
 
-use libzfs_types::{LibZfsError, Result};
+use libzfs::last_error;
+use libzfs_types::{
+    AttachError, AttachResult, ExportError, ExportResult, LibZfsError, RemovalStats, Result,
+    ScanFunction, ScanState, ScanStats, VDevState, ZfsErrorKind,
+};
 use nvpair;
 use std::ffi::{CStr, CString};
-use std::io::Error;
+use std::fs::{self, File};
+use std::io::{Error, ErrorKind, Seek, SeekFrom};
 use std::os::raw::{c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 use std::ptr;
-use vdev::{enumerate_vdev_tree, VDev};
-use zfs::Zfs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use vdev::{enumerate_vdev_tree, vdev_to_nv_list, VDev};
+use zfs::{self, DatasetIter, ListOptions, Zfs};
+
+/// Controls how a pool is exported.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExportOptions {
+    /// Forcefully unmount datasets that are in use, like `zpool export -f`.
+    pub force: bool,
+    /// Export without clearing the pool state in the labels,
+    /// like `zpool export -F`. Used when failing a pool over to another node.
+    pub hardforce: bool,
+    /// Recorded in the pool history.
+    pub log_str: Option<String>,
+}
+
+/// An action on a pool scrub.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanCommand {
+    /// Starts a scrub, or resumes a paused one.
+    Start,
+    Pause,
+    Cancel,
+}
+
+fn to_scan_state(state: u64) -> ScanState {
+    match state as sys::dsl_scan_state_t {
+        sys::dsl_scan_state_DSS_SCANNING => ScanState::Scanning,
+        sys::dsl_scan_state_DSS_FINISHED => ScanState::Finished,
+        sys::dsl_scan_state_DSS_CANCELED => ScanState::Canceled,
+        _ => ScanState::None,
+    }
+}
+
+fn to_removal_stats(xs: Vec<u64>) -> RemovalStats {
+    let x = sys::to_pool_removal_stat(xs);
+
+    RemovalStats {
+        state: to_scan_state(x.prs_state),
+        removing_vdev: x.prs_removing_vdev,
+        start_time: x.prs_start_time,
+        end_time: x.prs_end_time,
+        to_copy: x.prs_to_copy,
+        copied: x.prs_copied,
+        mapping_memory: x.prs_mapping_memory,
+    }
+}
+
+fn to_scan_stats(xs: Vec<u64>) -> ScanStats {
+    let x = sys::to_pool_scan_stat(xs);
+
+    let function = match x.pss_func as sys::pool_scan_func_t {
+        sys::pool_scan_func_POOL_SCAN_SCRUB => ScanFunction::Scrub,
+        sys::pool_scan_func_POOL_SCAN_RESILVER => ScanFunction::Resilver,
+        _ => ScanFunction::None,
+    };
+
+    let mut stats = ScanStats {
+        function,
+        state: to_scan_state(x.pss_state),
+        start_time: x.pss_start_time,
+        end_time: x.pss_end_time,
+        to_examine: x.pss_to_examine,
+        examined: x.pss_examined,
+        to_process: x.pss_to_process,
+        processed: x.pss_processed,
+        errors: x.pss_errors,
+        pass_examined: x.pss_pass_exam,
+        pass_start: x.pss_pass_start,
+        pass_scrub_pause: x.pss_pass_scrub_pause,
+        pass_scrub_spent_paused: x.pss_pass_scrub_spent_paused,
+        progress: None,
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+
+    stats.progress = stats.progress_at(now);
+
+    stats
+}
+
+/// Flags for bringing a vdev online.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OnlineFlags {
+    /// Expand the device to use all available space, like `zpool online -e`.
+    pub expand: bool,
+    /// Detach the hot spare standing in for this device.
+    pub unspare: bool,
+    /// Only online the device if it was removed and has come back.
+    pub check_remove: bool,
+    /// Fault the device instead of onlining it.
+    pub force_fault: bool,
+}
+
+impl OnlineFlags {
+    fn bits(&self) -> c_int {
+        let mut flags = 0;
+
+        if self.expand {
+            flags |= sys::ZFS_ONLINE_EXPAND;
+        }
+
+        if self.unspare {
+            flags |= sys::ZFS_ONLINE_UNSPARE;
+        }
+
+        if self.check_remove {
+            flags |= sys::ZFS_ONLINE_CHECKREMOVE;
+        }
+
+        if self.force_fault {
+            flags |= sys::ZFS_ONLINE_FORCEFAULT;
+        }
+
+        flags as c_int
+    }
+}
+
+fn to_vdev_state(state: sys::vdev_state_t) -> VDevState {
+    match state {
+        sys::vdev_state_VDEV_STATE_CLOSED => VDevState::Closed,
+        sys::vdev_state_VDEV_STATE_OFFLINE => VDevState::Offline,
+        sys::vdev_state_VDEV_STATE_REMOVED => VDevState::Removed,
+        sys::vdev_state_VDEV_STATE_CANT_OPEN => VDevState::CantOpen,
+        sys::vdev_state_VDEV_STATE_FAULTED => VDevState::Faulted,
+        sys::vdev_state_VDEV_STATE_DEGRADED => VDevState::Degraded,
+        sys::vdev_state_VDEV_STATE_HEALTHY => VDevState::Healthy,
+        _ => VDevState::Unknown,
+    }
+}
+
+fn to_attach_error(err: LibZfsError) -> AttachError {
+    match err {
+        LibZfsError::Zfs(ZfsErrorKind::NoReplicas, _) => AttachError::NoValidReplicas(err),
+        LibZfsError::Zfs(ZfsErrorKind::BadTarget, _) => AttachError::BadTarget(err),
+        _ => AttachError::Other(err),
+    }
+}
+
+/// ZFS rounds a device down to whole labels before sizing it
+const VDEV_LABEL_SIZE: u64 = 256 * 1024;
+const SPA_MINBLOCKSHIFT: u64 = 9;
+
+/// The ashift ZFS 0.7 picks for a disk, from its physical sector size.
+fn disk_ashift(path: &Path) -> Option<u64> {
+    let dev = fs::canonicalize(path).ok()?;
+    let block = fs::canonicalize(Path::new("/sys/class/block").join(dev.file_name()?)).ok()?;
+
+    // Partitions use the queue of their disk
+    let queue = match block.join("queue") {
+        ref x if x.exists() => x.clone(),
+        _ => block.parent()?.join("queue"),
+    };
+
+    let size: u64 = fs::read_to_string(queue.join("physical_block_size"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(63 - u64::from(size.max(1 << SPA_MINBLOCKSHIFT).leading_zeros()))
+}
+
+/// The path and size of a new disk or file vdev, as compared against
+/// `VDevStats::rsize`, and the ashift it would be opened with.
+fn new_vdev_geometry(vdev: &VDev) -> Result<(&Path, u64, Option<u64>)> {
+    let (path, ashift) = match *vdev {
+        VDev::Disk {
+            ref path, ashift, ..
+        } => (path, ashift.or_else(|| disk_ashift(path))),
+        VDev::File {
+            ref path, ashift, ..
+        } => (path, ashift.or(Some(SPA_MINBLOCKSHIFT))),
+        _ => {
+            return Err(LibZfsError::Io(Error::new(
+                ErrorKind::InvalidInput,
+                "new device must be a single disk or file",
+            )))
+        }
+    };
+
+    let size = File::open(path)?.seek(SeekFrom::End(0))?;
+
+    Ok((path, size - size % VDEV_LABEL_SIZE, ashift))
+}
+
+fn has_guid(tree: &nvpair::NvListRef, guid: u64) -> bool {
+    if tree.lookup_uint64(sys::zpool_config_guid()).ok() == Some(guid) {
+        return true;
+    }
+
+    tree.lookup_nv_list_array(sys::zpool_config_children())
+        .map(|xs| xs.iter().any(|x| has_guid(x, guid)))
+        .unwrap_or(false)
+}
+
+fn to_boolean(x: bool) -> sys::boolean::Type {
+    if x {
+        sys::boolean::B_TRUE
+    } else {
+        sys::boolean::B_FALSE
+    }
+}
+
+fn to_export_error(err: LibZfsError) -> ExportError {
+    match err {
+        LibZfsError::Zfs(ZfsErrorKind::Busy, _)
+        | LibZfsError::Zfs(ZfsErrorKind::UmountFailed, _) => ExportError::Busy(err),
+        err => ExportError::Other(err),
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Zpool {
@@ -142,6 +361,16 @@ impl Zpool {
             x => Err(LibZfsError::Io(Error::from_raw_os_error(x))),
         }
     }
+    /// Walks the pool's root dataset and everything below it, like `zfs list -r`.
+    ///
+    /// Unlike `datasets`, this includes the root dataset and
+    /// descends past its direct children.
+    pub fn list_datasets(&self, options: &ListOptions) -> Result<DatasetIter> {
+        let h = unsafe { sys::zpool_get_handle(self.raw) };
+        let root = zfs::open(h, &self.name().to_string_lossy(), sys::zfs_type_dataset())?;
+
+        Ok(DatasetIter::new(root, options))
+    }
     pub fn disable_datasets(&self) -> Result<()> {
         let code = unsafe { sys::zpool_disable_datasets(self.raw, sys::boolean::B_FALSE) };
 
@@ -150,12 +379,372 @@ impl Zpool {
             e => Err(LibZfsError::Io(Error::from_raw_os_error(e))),
         }
     }
+    /// Brings a vdev online, like `zpool online`.
+    ///
+    /// `vdev` is a device path, or the vdev guid as a decimal string.
+    pub fn online_vdev(&self, vdev: &str, flags: OnlineFlags) -> Result<VDevState> {
+        let vdev = CString::new(vdev).unwrap();
+        let mut state = sys::vdev_state_VDEV_STATE_UNKNOWN;
+
+        let code =
+            unsafe { sys::zpool_vdev_online(self.raw, vdev.as_ptr(), flags.bits(), &mut state) };
+
+        match code {
+            0 => Ok(to_vdev_state(state)),
+            _ => Err(last_error(unsafe { sys::zpool_get_handle(self.raw) })),
+        }
+    }
+    /// Takes a vdev offline, like `zpool offline`.
+    ///
+    /// A `temporary` offline does not persist across reboots.
+    pub fn offline_vdev(&self, vdev: &str, temporary: bool) -> Result<VDevState> {
+        let vdev = CString::new(vdev).unwrap();
+
+        let code =
+            unsafe { sys::zpool_vdev_offline(self.raw, vdev.as_ptr(), to_boolean(temporary)) };
+
+        if code != 0 {
+            return Err(last_error(unsafe { sys::zpool_get_handle(self.raw) }));
+        }
+
+        self.vdev_state(Some(&vdev))
+    }
+    /// Attaches `new_vdev` to the `existing` device, like `zpool attach`.
+    ///
+    /// When `replacing` is set, `existing` is detached once the new device
+    /// has resilvered, like `zpool replace`.
+    ///
+    /// libzfs reports a too small and a misaligned device alike,
+    /// so both are checked against `existing` before attaching.
+    pub fn attach(&self, existing: &str, new_vdev: &VDev, replacing: bool) -> AttachResult {
+        let nvroot = vdev_to_nv_list(&VDev::Root {
+            children: vec![new_vdev.clone()],
+            spares: vec![],
+            cache: vec![],
+            stats: None,
+        })
+        .map_err(AttachError::Other)?;
+
+        let (new_path, size, ashift) = new_vdev_geometry(new_vdev).map_err(AttachError::Other)?;
+
+        let existing = CString::new(existing).unwrap();
+        let new_path = CString::new(new_path.as_os_str().as_bytes()).unwrap();
+
+        if let Some((rsize, top_ashift)) = self.attach_target(&existing) {
+            if size < rsize {
+                return Err(AttachError::DeviceTooSmall(LibZfsError::Io(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("new device has {} bytes, {} are needed", size, rsize),
+                ))));
+            }
+
+            match (ashift, top_ashift) {
+                (Some(x), Some(y)) if x > y => {
+                    return Err(AttachError::SectorSizeMismatch(LibZfsError::Io(
+                        Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("new device needs ashift {}, the vdev has {}", x, y),
+                        ),
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        let code = unsafe {
+            sys::zpool_vdev_attach(
+                self.raw,
+                existing.as_ptr(),
+                new_path.as_ptr(),
+                nvroot.as_ptr() as *mut _,
+                replacing as c_int,
+            )
+        };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(to_attach_error(last_error(unsafe {
+                sys::zpool_get_handle(self.raw)
+            }))),
+        }
+    }
+    /// Adds the vdevs in a `VDev::Root` layout to this pool, like `zpool add`.
+    ///
+    /// Top-level children with `is_log` set are added as log devices,
+    /// and `spares` and `cache` as hot spares and L2ARC devices.
+    pub fn add_vdevs(&self, vdev: &VDev) -> Result<()> {
+        match *vdev {
+            VDev::Root { .. } => {}
+            _ => {
+                return Err(LibZfsError::Io(Error::new(
+                    ErrorKind::InvalidInput,
+                    "vdevs to add must be in a root vdev",
+                )))
+            }
+        }
+
+        let nvroot = vdev_to_nv_list(vdev)?;
+
+        let code = unsafe { sys::zpool_add(self.raw, nvroot.as_ptr() as *mut _) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(last_error(unsafe { sys::zpool_get_handle(self.raw) })),
+        }
+    }
+    /// Removes a device from this pool, like `zpool remove`.
+    ///
+    /// `vdev` is a device path, or the vdev guid as a decimal string.
+    /// ZFS 0.7 can only remove log, cache and hot spare devices, and does so
+    /// synchronously. Later versions can also remove top-level data vdevs,
+    /// evacuating them in the background; see `removal_status`.
+    pub fn remove_vdev(&self, vdev: &str) -> Result<()> {
+        let vdev = CString::new(vdev).unwrap();
+
+        let code = unsafe { sys::zpool_vdev_remove(self.raw, vdev.as_ptr()) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(last_error(unsafe { sys::zpool_get_handle(self.raw) })),
+        }
+    }
+    /// Refreshes the pool config and reads the progress of the current or
+    /// last top-level vdev removal, or `None` if there has not been one.
+    ///
+    /// ZFS 0.7 never reports one, as it removes devices synchronously.
+    pub fn removal_status(&self) -> Result<Option<RemovalStats>> {
+        let mut missing = sys::boolean::B_FALSE;
+
+        let code = unsafe { sys::zpool_refresh_stats(self.raw, &mut missing) };
+
+        if code != 0 {
+            return Err(last_error(unsafe { sys::zpool_get_handle(self.raw) }));
+        }
+
+        let tree = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?;
+
+        Ok(tree
+            .lookup_uint64_array(sys::zpool_config_removing_stats())
+            .map(to_removal_stats)
+            .ok())
+    }
+    /// Replaces the `existing` device with `new_vdev`, like `zpool replace`.
+    pub fn replace(&self, existing: &str, new_vdev: &VDev) -> AttachResult {
+        self.attach(existing, new_vdev, true)
+    }
+    /// Detaches a device from a mirror, or cancels a replacement,
+    /// like `zpool detach`.
+    pub fn detach(&self, vdev: &str) -> AttachResult {
+        let vdev = CString::new(vdev).unwrap();
+
+        let code = unsafe { sys::zpool_vdev_detach(self.raw, vdev.as_ptr()) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(to_attach_error(last_error(unsafe {
+                sys::zpool_get_handle(self.raw)
+            }))),
+        }
+    }
+    /// Clears device errors, like `zpool clear`.
+    ///
+    /// Errors on every vdev are cleared when `vdev` is `None`,
+    /// and the state of the pool's root vdev is returned.
+    pub fn clear(&self, vdev: Option<&str>) -> Result<VDevState> {
+        let vdev = vdev.map(|x| CString::new(x).unwrap());
+        let policy = nvpair::NvList::new_unique_names()?;
+
+        let code = unsafe {
+            sys::zpool_clear(
+                self.raw,
+                vdev.as_ref().map_or(ptr::null(), |x| x.as_ptr()),
+                policy.as_ptr() as *mut _,
+            )
+        };
+
+        if code != 0 {
+            return Err(last_error(unsafe { sys::zpool_get_handle(self.raw) }));
+        }
+
+        self.vdev_state(vdev.as_deref())
+    }
+    /// The replaceable size of the leaf `vdev`, and the ashift of its
+    /// top-level vdev. `None` if it can't be found, leaving libzfs to
+    /// report why.
+    fn attach_target(&self, vdev: &CStr) -> Option<(u64, Option<u64>)> {
+        let mut spare = sys::boolean::B_FALSE;
+        let mut l2cache = sys::boolean::B_FALSE;
+        let mut log = sys::boolean::B_FALSE;
+
+        let leaf = unsafe {
+            sys::zpool_find_vdev(self.raw, vdev.as_ptr(), &mut spare, &mut l2cache, &mut log)
+        };
+
+        if leaf.is_null() {
+            return None;
+        }
+
+        let leaf = unsafe { nvpair::NvListRef::from_mut_ptr(leaf) };
+
+        let rsize = leaf
+            .lookup_uint64_array(sys::zpool_config_vdev_stats())
+            .map(sys::to_vdev_stat)
+            .ok()?
+            .vs_rsize;
+
+        let guid = leaf.lookup_uint64(sys::zpool_config_guid()).ok()?;
+
+        let top = self
+            .get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())
+            .ok()?
+            .lookup_nv_list_array(sys::zpool_config_children())
+            .ok()?
+            .into_iter()
+            .find(|x| has_guid(x, guid))?;
+
+        Some((rsize, top.lookup_uint64(sys::zpool_config_ashift()).ok()))
+    }
+    /// Refreshes the pool config and reads the current state of `vdev`,
+    /// or of the root vdev when `vdev` is `None`.
+    fn vdev_state(&self, vdev: Option<&CStr>) -> Result<VDevState> {
+        let mut missing = sys::boolean::B_FALSE;
+
+        let code = unsafe { sys::zpool_refresh_stats(self.raw, &mut missing) };
+
+        if code != 0 {
+            return Err(last_error(unsafe { sys::zpool_get_handle(self.raw) }));
+        }
+
+        let tree = match vdev {
+            Some(x) => {
+                let mut spare = sys::boolean::B_FALSE;
+                let mut l2cache = sys::boolean::B_FALSE;
+                let mut log = sys::boolean::B_FALSE;
+
+                let tree = unsafe {
+                    sys::zpool_find_vdev(self.raw, x.as_ptr(), &mut spare, &mut l2cache, &mut log)
+                };
+
+                if tree.is_null() {
+                    return Err(LibZfsError::Io(Error::new(
+                        ErrorKind::NotFound,
+                        "could not find vdev",
+                    )));
+                }
+
+                unsafe { nvpair::NvListRef::from_mut_ptr(tree) }
+            }
+            None => self
+                .get_config()
+                .lookup_nv_list_ref(sys::zpool_config_vdev_tree())?,
+        };
+
+        let stats = tree
+            .lookup_uint64_array(sys::zpool_config_vdev_stats())
+            .map(sys::to_vdev_stat)?;
+
+        Ok(to_vdev_state(stats.vs_state as sys::vdev_state_t))
+    }
+    /// Starts, pauses or cancels a scrub of this pool.
+    pub fn scrub(&self, command: ScanCommand) -> Result<()> {
+        let (func, cmd) = match command {
+            ScanCommand::Start => (
+                sys::pool_scan_func_POOL_SCAN_SCRUB,
+                sys::pool_scrub_cmd_POOL_SCRUB_NORMAL,
+            ),
+            ScanCommand::Pause => (
+                sys::pool_scan_func_POOL_SCAN_SCRUB,
+                sys::pool_scrub_cmd_POOL_SCRUB_PAUSE,
+            ),
+            ScanCommand::Cancel => (
+                sys::pool_scan_func_POOL_SCAN_NONE,
+                sys::pool_scrub_cmd_POOL_SCRUB_NORMAL,
+            ),
+        };
+
+        let code = unsafe { sys::zpool_scan(self.raw, func, cmd) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(last_error(unsafe { sys::zpool_get_handle(self.raw) })),
+        }
+    }
+    /// Refreshes the pool config and reads the progress of the current
+    /// or last scan of this pool, or `None` if the pool has never been
+    /// scanned or could not be refreshed.
+    pub fn scan_status(&self) -> Option<ScanStats> {
+        let mut missing = sys::boolean::B_FALSE;
+
+        if unsafe { sys::zpool_refresh_stats(self.raw, &mut missing) } != 0 {
+            return None;
+        }
+
+        self.get_config()
+            .lookup_nv_list_ref(sys::zpool_config_vdev_tree())
+            .and_then(|x| x.lookup_uint64_array(sys::zpool_config_scan_stats()))
+            .map(to_scan_stats)
+            .ok()
+    }
+    /// Unmounts all datasets and exports this pool with the default options.
     pub fn export(&self) -> Result<()> {
-        let code = unsafe { sys::zpool_export(self.raw, sys::boolean::B_FALSE, ptr::null_mut()) };
+        self.export_with(&ExportOptions::default())
+            .map_err(LibZfsError::from)
+    }
+    /// Unmounts all datasets and exports this pool.
+    ///
+    /// Failures caused by the pool or a dataset being in use
+    /// are returned as `ExportError::Busy`.
+    pub fn export_with(&self, options: &ExportOptions) -> ExportResult {
+        let hdl = unsafe { sys::zpool_get_handle(self.raw) };
+        let force = to_boolean(options.force);
+
+        let code = unsafe { sys::zpool_disable_datasets(self.raw, force) };
+
+        if code != 0 {
+            return Err(to_export_error(last_error(hdl)));
+        }
+
+        let log_str = options
+            .log_str
+            .as_ref()
+            .map(|x| CString::new(x.as_str()).unwrap());
+        let log_str = log_str.as_ref().map_or(ptr::null(), |x| x.as_ptr());
+
+        let code = unsafe {
+            if options.hardforce {
+                sys::zpool_export_force(self.raw, log_str)
+            } else {
+                sys::zpool_export(self.raw, force, log_str)
+            }
+        };
 
         match code {
             0 => Ok(()),
-            e => Err(LibZfsError::Io(Error::from_raw_os_error(e))),
+            _ => Err(to_export_error(last_error(hdl))),
+        }
+    }
+    /// Unmounts all datasets and destroys this pool.
+    ///
+    /// `force` forcefully unmounts datasets that are in use.
+    /// `log_history` is recorded in the pool history.
+    /// The handle is consumed, as the pool no longer exists afterwards.
+    pub fn destroy(self, force: bool, log_history: &str) -> Result<()> {
+        let code = unsafe { sys::zpool_disable_datasets(self.raw, to_boolean(force)) };
+
+        if code != 0 {
+            return Err(last_error(unsafe { sys::zpool_get_handle(self.raw) }));
+        }
+
+        let log_history = CString::new(log_history).unwrap();
+
+        let code = unsafe { sys::zpool_destroy(self.raw, log_history.as_ptr()) };
+
+        match code {
+            0 => Ok(()),
+            _ => Err(last_error(unsafe { sys::zpool_get_handle(self.raw) })),
         }
     }
 }
@@ -170,16 +759,21 @@ impl Drop for Zpool {
 mod tests {
     use super::*;
     use libzfs::Libzfs;
+    use libzfs_types::{AllocationClass, ZProp};
+    use test_pool::{file_vdev, lock_pools, TestPool};
 
-    use std::{ffi::CString, panic, path::PathBuf, str};
+    use std::{ffi::CString, fs, panic, path::PathBuf, str};
 
-    fn test_pools<F: Fn(&Vec<Zpool>) -> ()>(f: F) -> ()
+    fn test_pools<F>(f: F)
     where
-        F: panic::RefUnwindSafe,
+        F: Fn(&Vec<Zpool>) + panic::RefUnwindSafe,
     {
+        let _lock = lock_pools();
         let mut z = Libzfs::new();
 
-        let pools_to_import = z.find_importable_pools();
+        let pools_to_import = z
+            .find_importable_pools()
+            .expect("could not find importable pools");
 
         z.import_all(&pools_to_import)
             .expect("could not import pools");
@@ -195,9 +789,9 @@ mod tests {
         result.unwrap();
     }
 
-    fn pool_by_name<F: Fn(&Zpool) -> ()>(name: &str, f: F) -> ()
+    fn pool_by_name<F>(name: &str, f: F)
     where
-        F: panic::RefUnwindSafe,
+        F: Fn(&Zpool) + panic::RefUnwindSafe,
     {
         test_pools(|xs| {
             let x = xs
@@ -238,7 +832,7 @@ mod tests {
 
     #[test]
     fn get_pool_read_only() {
-        pool_by_name("test", |p| assert_eq!(p.read_only(), false))
+        pool_by_name("test", |p| assert!(!p.read_only()))
     }
 
     #[test]
@@ -256,6 +850,53 @@ mod tests {
         pool_by_name("test", |p| assert!(p.hostid().is_ok()))
     }
 
+    #[test]
+    fn scrub_pause_cancel() {
+        let fixture = TestPool::new("scrub", 2);
+        let mut z = Libzfs::new();
+
+        let mnt = fixture.paths[0].with_file_name("mnt");
+
+        let pool = z
+            .create_pool(
+                &fixture.name,
+                &fixture.stripe(),
+                &[],
+                &[ZProp {
+                    name: "mountpoint".to_string(),
+                    value: mnt.to_string_lossy().into_owned(),
+                }],
+            )
+            .expect("could not create pool");
+
+        // A scrub of an empty pool can finish before it is paused
+        let code = unsafe { sys::zpool_enable_datasets(pool.raw, ptr::null(), 0) };
+        assert_eq!(code, 0);
+
+        fs::write(mnt.join("data"), vec![0xa5; 96 * 1024 * 1024]).expect("could not write data");
+
+        let status = |p: &Zpool| p.scan_status().expect("could not get scan status");
+
+        pool.scrub(ScanCommand::Start)
+            .expect("could not start scrub");
+        pool.scrub(ScanCommand::Pause)
+            .expect("could not pause scrub");
+
+        let stats = status(&pool);
+        assert_eq!(stats.function, ScanFunction::Scrub);
+        assert!(stats.is_paused());
+
+        pool.scrub(ScanCommand::Cancel)
+            .expect("could not cancel scrub");
+
+        let stats = status(&pool);
+        assert_eq!(stats.state, ScanState::Canceled);
+        assert!(!stats.is_paused());
+
+        pool.scrub(ScanCommand::Cancel)
+            .expect_err("cancelled a scrub that was not running");
+    }
+
     #[test]
     fn test_vdev_tree() {
         fn create_path_buf(s: &str) -> PathBuf {
@@ -271,6 +912,7 @@ mod tests {
                     children,
                     cache,
                     spares,
+                    ..
                 } => (children, cache, spares),
                 _ => panic!("did not find root device"),
             };
@@ -289,8 +931,11 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ref stats,
+                    ..
                 } => {
                     assert!(guid.is_some());
+                    assert_eq!(stats.as_ref().map(|x| x.checksum_errors), Some(0));
                     assert_eq!(state, "ONLINE");
                     assert_eq!(path, &create_path_buf("/dev/sdb1"));
                     assert!(dev_id.is_some());
@@ -310,8 +955,11 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ref stats,
+                    ..
                 } => {
                     assert!(guid.is_some());
+                    assert_eq!(stats.as_ref().map(|x| x.checksum_errors), Some(0));
                     assert_eq!(state, "ONLINE");
                     assert_eq!(path, &create_path_buf("/dev/sdc1"));
                     assert!(dev_id.is_some());
@@ -331,8 +979,11 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ref stats,
+                    ..
                 } => {
                     assert!(guid.is_some());
+                    assert_eq!(stats.as_ref().map(|x| x.checksum_errors), Some(0));
                     assert_eq!(state, "ONLINE");
                     assert_eq!(path, &create_path_buf("/dev/sdd1"));
                     assert!(dev_id.is_some());
@@ -352,8 +1003,11 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ref stats,
+                    ..
                 } => {
                     assert!(guid.is_some());
+                    assert_eq!(stats.as_ref().map(|x| x.checksum_errors), Some(0));
                     assert_eq!(state, "ONLINE");
                     assert_eq!(path, &create_path_buf("/dev/sde1"));
                     assert!(dev_id.is_some());
@@ -373,8 +1027,11 @@ mod tests {
                     ref phys_path,
                     whole_disk,
                     is_log,
+                    ref stats,
+                    ..
                 } => {
                     assert!(guid.is_some());
+                    assert_eq!(stats.as_ref().map(|x| x.checksum_errors), Some(0));
                     assert_eq!(state, "ONLINE");
                     assert_eq!(path, &create_path_buf("/dev/sdf1"));
                     assert!(dev_id.is_some());
@@ -386,4 +1043,172 @@ mod tests {
             };
         })
     }
+
+    #[test]
+    fn offline_online_clear_vdev() {
+        let fixture = TestPool::new("vdev_state", 2);
+        let mut z = Libzfs::new();
+
+        let pool = fixture.create(&mut z, &fixture.mirror());
+        let path = fixture.paths[0].to_str().unwrap();
+
+        assert_eq!(
+            pool.offline_vdev(path, true)
+                .expect("could not offline vdev"),
+            VDevState::Offline
+        );
+
+        assert_eq!(
+            pool.online_vdev(path, OnlineFlags::default())
+                .expect("could not online vdev"),
+            VDevState::Healthy
+        );
+
+        assert_eq!(
+            pool.clear(None).expect("could not clear pool"),
+            VDevState::Healthy
+        );
+    }
+
+    #[test]
+    fn attach_detach_replace_vdev() {
+        let fixture = TestPool::new("attach", 3);
+        let paths = &fixture.paths;
+        let mut z = Libzfs::new();
+
+        let layout = VDev::Root {
+            children: vec![file_vdev(&paths[0])],
+            spares: vec![],
+            cache: vec![],
+            stats: None,
+        };
+
+        let pool = fixture.create(&mut z, &layout);
+        let existing = paths[0].to_str().unwrap();
+
+        pool.attach(existing, &file_vdev(&paths[1]), false)
+            .expect("could not attach vdev");
+
+        match pool.vdev_tree().expect("could not get vdev tree") {
+            VDev::Root { ref children, .. } => match children[0] {
+                VDev::Mirror { ref children, .. } => assert_eq!(children.len(), 2),
+                _ => panic!("did not find mirror"),
+            },
+            _ => panic!("did not find root device"),
+        };
+
+        pool.detach(paths[1].to_str().unwrap())
+            .expect("could not detach vdev");
+
+        pool.replace(existing, &file_vdev(&paths[2]))
+            .expect("could not replace vdev");
+    }
+
+    #[test]
+    fn attach_too_small_vdev() {
+        let fixture = TestPool::new("attach_small", 1);
+        let small = fixture.file("small", 96 * 1024 * 1024);
+        let mut z = Libzfs::new();
+
+        let pool = fixture.create(&mut z, &fixture.stripe());
+        let existing = fixture.paths[0].to_str().unwrap();
+
+        match pool.attach(existing, &file_vdev(&small), false) {
+            Err(AttachError::DeviceTooSmall(_)) => {}
+            x => panic!("expected device too small, got {:?}", x),
+        }
+
+        let wide = VDev::File {
+            guid: None,
+            state: "UNKNOWN".to_string(),
+            path: fixture.file("wide", 128 * 1024 * 1024),
+            is_log: None,
+            alloc_class: None,
+            ashift: Some(12),
+            stats: None,
+        };
+
+        match pool.attach(existing, &wide, false) {
+            Err(AttachError::SectorSizeMismatch(_)) => {}
+            x => panic!("expected sector size mismatch, got {:?}", x),
+        }
+
+        match pool.detach(existing) {
+            Err(AttachError::BadTarget(_)) => {}
+            x => panic!("expected bad target, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn add_and_remove_vdevs() {
+        let fixture = TestPool::new("add_vdevs", 4);
+        let paths = &fixture.paths;
+        let mut z = Libzfs::new();
+
+        let layout = VDev::Root {
+            children: vec![file_vdev(&paths[0])],
+            spares: vec![],
+            cache: vec![],
+            stats: None,
+        };
+
+        let pool = fixture.create(&mut z, &layout);
+
+        let log = VDev::File {
+            guid: None,
+            state: "UNKNOWN".to_string(),
+            path: paths[1].clone(),
+            is_log: Some(true),
+            alloc_class: Some(AllocationClass::Log),
+            ashift: None,
+            stats: None,
+        };
+
+        let additions = VDev::Root {
+            children: vec![log],
+            spares: vec![file_vdev(&paths[2])],
+            cache: vec![file_vdev(&paths[3])],
+            stats: None,
+        };
+
+        pool.add_vdevs(&additions).expect("could not add vdevs");
+
+        match pool.vdev_tree().expect("could not get vdev tree") {
+            VDev::Root {
+                ref children,
+                ref spares,
+                ref cache,
+                ..
+            } => {
+                assert_eq!(children.len(), 2);
+                assert_eq!(spares.len(), 1);
+                assert_eq!(cache.len(), 1);
+            }
+            _ => panic!("did not find root device"),
+        };
+
+        for p in &paths[1..] {
+            pool.remove_vdev(p.to_str().unwrap())
+                .expect("could not remove vdev");
+        }
+
+        match pool.vdev_tree().expect("could not get vdev tree") {
+            VDev::Root {
+                ref children,
+                ref spares,
+                ref cache,
+                ..
+            } => {
+                assert_eq!(children.len(), 1);
+                assert!(spares.is_empty());
+                assert!(cache.is_empty());
+            }
+            _ => panic!("did not find root device"),
+        };
+
+        assert_eq!(
+            pool.removal_status().expect("could not get removal status"),
+            None
+        );
+    }
 }
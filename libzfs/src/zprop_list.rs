@@ -3,10 +3,28 @@
 // license that can be found in the LICENSE file.
 
 extern crate libzfs_sys as sys;
-use std::ffi::CStr;
+use libzfs_types::Result;
+use nvpair;
+use nvpair::NvEncode;
+use std::ffi::{CStr, CString};
+use std::io::Error;
 
 pub use libzfs_types::ZProp;
 
+/// Converts a list of `ZProp` into an `NvList` of string values,
+/// the form libzfs expects for pool and dataset properties.
+pub fn to_nv_list(props: &[ZProp]) -> Result<nvpair::NvList> {
+    let mut nvl = nvpair::NvList::new_unique_names()?;
+
+    for p in props {
+        let value = CString::new(p.value.as_str()).map_err(Error::from)?;
+
+        value.insert(p.name.as_str(), &mut nvl)?;
+    }
+
+    Ok(nvl)
+}
+
 #[derive(Debug, PartialEq)]
 pub struct ZpropList {
     head: *mut sys::zprop_list,